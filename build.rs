@@ -0,0 +1,70 @@
+//! Compiles the GLSL shaders into SPIR-V with `glslc`, or the compiler in the `GLSLC` variable.
+//!
+//! Cargo reruns the script whenever a shader or a file it includes changes, compilation errors
+//! fail the build with the compiler's diagnostics.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+#[path = "src/engine/utils/shader_compiler.rs"]
+mod shader_compiler;
+
+use shader_compiler::{is_shader_stage, parse_dependencies, ShaderCompiler, COMPILER_ENV};
+
+const SHADER_SOURCES_DIR: &str = "src/engine/renderer/shaders";
+
+fn main() {
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let output_dir = out_dir.join("shaders");
+    fs::create_dir_all(&output_dir).unwrap();
+
+    println!("cargo:rerun-if-changed={SHADER_SOURCES_DIR}");
+    println!("cargo:rerun-if-env-changed={COMPILER_ENV}");
+    println!(
+        "cargo:rustc-env=COMPILED_SHADERS_DIR={}",
+        output_dir.display()
+    );
+
+    let compiler = ShaderCompiler::new(SHADER_SOURCES_DIR);
+    let mut sources: Vec<_> = fs::read_dir(SHADER_SOURCES_DIR)
+        .unwrap()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_shader_stage(path))
+        .collect();
+    sources.sort();
+
+    let errors: Vec<_> = sources
+        .iter()
+        .filter_map(|source| compile(&compiler, source, &output_dir).err())
+        .collect();
+
+    if !errors.is_empty() {
+        panic!(
+            "Failed to compile {} shaders:\n\n{}",
+            errors.len(),
+            errors.join("\n\n")
+        );
+    }
+}
+
+/// Compiles the shader into `<output_dir>/<file name>.spv`, returns the diagnostics if it fails.
+fn compile(compiler: &ShaderCompiler, source: &Path, output_dir: &Path) -> Result<(), String> {
+    let file_name = source.file_name().unwrap().to_string_lossy();
+    let output = output_dir.join(format!("{file_name}.spv"));
+    let dependency_file = output_dir.join(format!("{file_name}.d"));
+
+    let result = compiler.compile(source, &output, Some(&dependency_file));
+
+    // NOTE: Includes are watched even if the compilation failed, so fixing them triggers a rebuild.
+    fs::read_to_string(&dependency_file)
+        .map(|dependencies| {
+            parse_dependencies(&dependencies)
+                .iter()
+                .for_each(|dependency| println!("cargo:rerun-if-changed={dependency}"))
+        })
+        .ok();
+
+    result.map_err(|error| format!("{}:\n{}", source.display(), error.0.trim_end()))
+}
@@ -0,0 +1,310 @@
+//! Cooks the source assets into the engine's runtime formats, so shipping builds never parse
+//! OBJ, glTF, images or GLSL.
+//!
+//! Usage: `cooker [SOURCE_DIR] [OUTPUT_DIR] [--force]`, by default the assets directory is cooked
+//! into the cooked directory, together with the engine's shaders.
+//!
+//! Every cooked file mirrors its source with the extension appended, e.g. `models/cube.obj.vlmodel`.
+//! A manifest in the output directory remembers the stamp and the content hash of every cooked source:
+//! sources whose stamp or content didn't change are skipped, `--force` cooks everything again.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt::Write as _,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+use tracing::{error, info};
+use track::Context;
+use vulkan_learning::engine::{
+    asset_system::{
+        model::{self, Model},
+        texture::{self, Texture},
+    },
+    utils::{paths, shader_compiler::ShaderCompiler},
+};
+use walkdir::WalkDir;
+
+const MANIFEST_NAME: &str = "manifest.txt";
+const SHADER_EXTENSION: &str = "spv";
+
+struct CookerArgs {
+    source_dir: PathBuf,
+    output_dir: PathBuf,
+    force: bool,
+}
+
+impl CookerArgs {
+    fn from_env() -> Self {
+        let mut dirs = Vec::with_capacity(2);
+        let mut force = false;
+
+        for arg in std::env::args().skip(1) {
+            match arg.as_str() {
+                "--force" => force = true,
+                _ if arg.starts_with("--") => panic!("Unknown argument: {arg}"),
+                _ if dirs.len() < 2 => dirs.push(PathBuf::from(arg)),
+                _ => panic!("Unexpected argument: {arg}"),
+            }
+        }
+
+        let mut dirs = dirs.into_iter();
+
+        Self {
+            source_dir: dirs.next().unwrap_or_else(paths::assets_dir),
+            output_dir: dirs.next().unwrap_or_else(paths::cooked_dir),
+            force,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum AssetKind {
+    Model,
+    Texture,
+    Shader,
+}
+
+impl AssetKind {
+    fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            "obj" | "gltf" | "glb" => Some(Self::Model),
+            "png" | "jpg" | "jpeg" | "tga" => Some(Self::Texture),
+            "vert" | "frag" | "comp" => Some(Self::Shader),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    const fn cooked_extension(self) -> &'static str {
+        match self {
+            Self::Model => model::cache::EXTENSION,
+            Self::Texture => texture::cache::EXTENSION,
+            Self::Shader => SHADER_EXTENSION,
+        }
+    }
+}
+
+/// Source file and where it's cooked to.
+struct Job {
+    kind: AssetKind,
+    source: PathBuf,
+    cooked: PathBuf,
+    /// Path of the cooked file relative to the output directory, the key in the manifest.
+    key: String,
+}
+
+/// What the manifest remembers about the source of a cooked file.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct ManifestEntry {
+    stamp: model::cache::SourceStamp,
+    content_hash: u64,
+}
+
+enum Outcome {
+    Cooked,
+    UpToDate,
+    Failed,
+}
+
+fn main() {
+    tracing_subscriber::fmt::init();
+
+    let args = CookerArgs::from_env();
+    let manifest_path = args.output_dir.join(MANIFEST_NAME);
+    let manifest = match args.force {
+        true => HashMap::new(),
+        false => read_manifest(&manifest_path),
+    };
+
+    let mut jobs = collect_jobs(&args.source_dir, &args.output_dir);
+    // NOTE: The engine's shaders are only cooked along the default assets.
+    if args.source_dir == paths::assets_dir() {
+        jobs.extend(collect_jobs(
+            &paths::shader_sources_dir(),
+            &args.output_dir.join(paths::COOKED_SHADERS_DIR),
+        ));
+    }
+
+    info!(
+        "Cooking {} assets from {:?} into {:?}",
+        jobs.len(),
+        args.source_dir,
+        args.output_dir
+    );
+
+    let results: Vec<_> = jobs
+        .par_iter()
+        .map(|job| {
+            let previous_entry = manifest.get(&job.key).copied();
+
+            match cook(job, previous_entry) {
+                Ok((entry, outcome)) => (job, Some(entry), outcome),
+                Err(error) => {
+                    error!("Failed to cook {:?}: {error}", job.source);
+
+                    (job, None, Outcome::Failed)
+                }
+            }
+        })
+        .collect();
+
+    // NOTE: Failed and removed sources are left out of the manifest, so they are cooked next time.
+    let new_manifest: HashMap<_, _> = results
+        .iter()
+        .filter_map(|(job, entry, _)| entry.map(|entry| (job.key.clone(), entry)))
+        .collect();
+    write_manifest(&manifest_path, &new_manifest).unwrap();
+
+    let count = |predicate: fn(&Outcome) -> bool| {
+        results
+            .iter()
+            .filter(|(_, _, outcome)| predicate(outcome))
+            .count()
+    };
+    let failed = count(|outcome| matches!(outcome, Outcome::Failed));
+
+    info!(
+        "Cooked {}, up to date {}, failed {failed}",
+        count(|outcome| matches!(outcome, Outcome::Cooked)),
+        count(|outcome| matches!(outcome, Outcome::UpToDate)),
+    );
+
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn collect_jobs(source_dir: &Path, output_dir: &Path) -> Vec<Job> {
+    WalkDir::new(source_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let source = entry.into_path();
+            let kind = AssetKind::from_path(&source)?;
+            let cooked =
+                paths::cooked_path(source_dir, output_dir, &source, kind.cooked_extension());
+            let key = cooked
+                .strip_prefix(output_dir)
+                .unwrap_or(&cooked)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            Some(Job {
+                kind,
+                source,
+                cooked,
+                key,
+            })
+        })
+        .collect()
+}
+
+/// Cooks the source unless its stamp or content match the manifest and the cooked file exists.
+fn cook(
+    job: &Job,
+    previous_entry: Option<ManifestEntry>,
+) -> track::Result<(ManifestEntry, Outcome)> {
+    let stamp = model::cache::SourceStamp::new(&job.source).track()?;
+    let is_cooked = job.cooked.exists();
+
+    if is_cooked && previous_entry.map(|entry| entry.stamp) == Some(stamp) {
+        return Ok((previous_entry.unwrap(), Outcome::UpToDate));
+    }
+
+    // NOTE: Only the file itself is hashed, files it references (buffers, materials)
+    // aren't tracked, `--force` cooks them again.
+    let mut hasher = DefaultHasher::new();
+    fs::read(&job.source).track()?.hash(&mut hasher);
+    let entry = ManifestEntry {
+        stamp,
+        content_hash: hasher.finish(),
+    };
+
+    // NOTE: A touched but unchanged source only refreshes its stamp.
+    if is_cooked && previous_entry.map(|entry| entry.content_hash) == Some(entry.content_hash) {
+        return Ok((entry, Outcome::UpToDate));
+    }
+
+    info!("Cooking {:?} into {:?}", job.source, job.cooked);
+    match job.kind {
+        AssetKind::Model => {
+            let model = Model::new(&job.source).track()?;
+            model::cache::write(&job.cooked, stamp, &model, Default::default()).track()?;
+        }
+        AssetKind::Texture => {
+            let texture = Texture::new(&job.source).track()?;
+            texture::cache::write(&job.cooked, stamp, &texture, Default::default()).track()?;
+        }
+        AssetKind::Shader => compile_shader(&job.source, &job.cooked).track()?,
+    }
+
+    Ok((entry, Outcome::Cooked))
+}
+
+/// Compiles the GLSL shader into SPIR-V, resolving includes against the engine's shaders.
+fn compile_shader(source: &Path, cooked: &Path) -> track::Result<()> {
+    if let Some(directory) = cooked.parent() {
+        fs::create_dir_all(directory).track()?;
+    }
+
+    ShaderCompiler::new(paths::shader_sources_dir())
+        .compile(source, cooked, None)
+        .track()
+}
+
+/// Reads the manifest, a missing or unreadable one is empty.
+///
+/// Every line is `<key> <len> <modified> <content hash>`, the key may contain spaces.
+fn read_manifest(path: &Path) -> HashMap<String, ManifestEntry> {
+    let Ok(manifest) = fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+
+    manifest
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.rsplitn(4, ' ');
+            let content_hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+            let modified = fields.next()?.parse().ok()?;
+            let len = fields.next()?.parse().ok()?;
+            let key = fields.next()?.to_owned();
+
+            Some((
+                key,
+                ManifestEntry {
+                    stamp: model::cache::SourceStamp { len, modified },
+                    content_hash,
+                },
+            ))
+        })
+        .collect()
+}
+
+fn write_manifest(path: &Path, manifest: &HashMap<String, ManifestEntry>) -> track::Result<()> {
+    let mut keys: Vec<_> = manifest.keys().collect();
+    keys.sort();
+
+    let mut content = String::new();
+    keys.into_iter().for_each(|key| {
+        let entry = manifest[key];
+        writeln!(
+            content,
+            "{key} {} {} {:016x}",
+            entry.stamp.len, entry.stamp.modified, entry.content_hash
+        )
+        .unwrap();
+    });
+
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).track()?;
+    }
+
+    fs::write(path, content).track()
+}
@@ -1,39 +1,193 @@
-mod asset_system;
+pub mod asset_system;
+mod camera;
 mod renderer;
-mod utils;
+pub mod utils;
 
-use ash::prelude::VkResult;
+pub use camera::{Camera, Projection};
+pub use renderer::{
+    BlendMode, ComputeBinding, ComputeDispatch, ComputePipelineId, DispatchSize, PipelineBuilder,
+    PipelineId, StorageBufferHandle, StorageImageHandle,
+};
 use smallvec::SmallVec;
 use tracing::info;
 use track::Context as TrackContext;
 
-use self::asset_system::mesh;
-
 pub struct Engine {
     renderer: renderer::Renderer,
-    meshes: SmallVec<[mesh::Mesh; Self::DEFAULT_STACK_BASED_MESHES_SIZE]>,
+    asset_system: asset_system::AssetSystem,
+    /// Models placed into the world, turned into objects every frame.
+    scene: Vec<(asset_system::Handle<asset_system::ModelAsset>, math::Mat4)>,
+    camera: Camera,
+    objects: SmallVec<[renderer::RenderObject; Self::DEFAULT_STACK_BASED_MESHES_SIZE]>,
 }
 
 impl Engine {
     pub const DEFAULT_STACK_BASED_MESHES_SIZE: usize = 1024;
+    pub const DEFAULT_MODEL: &str = "models/cube.obj";
+    pub const PLACEHOLDER_MODEL: &str = "models/placeholder.obj";
+    pub const PLACEHOLDER_TEXTURE: &str = "textures/checker.png";
 
     pub fn new(window: &winit::window::Window) -> track::Result<Self> {
         info!("Initializing Renderer");
-        let mut renderer = unsafe { renderer::Renderer::new(window).track()? };
+        let renderer =
+            unsafe { renderer::Renderer::new(window, renderer::DEFAULT_FRAMES_IN_FLIGHT).track()? };
+
+        Self::with_renderer(renderer, true)
+    }
 
-        let mut meshes = SmallVec::new();
+    /// Creates an engine without a window, its frames are written to disk by [`Engine::capture`].
+    ///
+    /// The scene is loaded before returning, so every captured frame shows it instead of
+    /// placeholders and frames compare across runs.
+    pub fn new_headless(width: u32, height: u32) -> track::Result<Self> {
+        info!("Initializing headless Renderer");
+        let renderer = unsafe {
+            renderer::Renderer::new_headless(
+                ash::vk::Extent2D { width, height },
+                renderer::DEFAULT_FRAMES_IN_FLIGHT,
+            )
+            .track()?
+        };
 
-        let mesh = renderer
-            .upload_asset(r"D:\Загрузки\test_models\monkey.obj")
+        Self::with_renderer(renderer, false)
+    }
+
+    fn with_renderer(
+        mut renderer: renderer::Renderer,
+        load_scene_async: bool,
+    ) -> track::Result<Self> {
+        let mut asset_system = asset_system::AssetSystem::default();
+
+        // NOTE: Placeholders are drawn until the models are resident, so they are loaded right away.
+        let placeholder_model = asset_system
+            .load_model(
+                &mut renderer,
+                utils::paths::assets_dir().join(Self::PLACEHOLDER_MODEL),
+            )
+            .track()?;
+        let placeholder_texture = asset_system
+            .load_texture(
+                &mut renderer,
+                utils::paths::assets_dir().join(Self::PLACEHOLDER_TEXTURE),
+            )
             .track()?;
+        asset_system.set_placeholders(placeholder_model, placeholder_texture);
+
+        let model_path = utils::paths::assets_dir().join(Self::DEFAULT_MODEL);
+        let model = match load_scene_async {
+            true => asset_system.load_model_async(model_path).track()?,
+            false => asset_system.load_model(&mut renderer, model_path).track()?,
+        };
+        let scene = vec![(model, math::Mat4::identity())];
+
+        let camera = Camera::default();
+
+        Ok(Self {
+            renderer,
+            asset_system,
+            scene,
+            camera,
+            objects: SmallVec::new(),
+        })
+    }
+
+    #[inline(always)]
+    pub fn draw(&mut self) -> track::Result<()> {
+        self.asset_system.update(&mut self.renderer).track()?;
 
-        meshes.push(mesh);
+        self.objects.clear();
+        self.objects.extend(
+            self.scene
+                .iter()
+                .flat_map(|(model, transform)| self.asset_system.render_objects(model, *transform)),
+        );
 
-        Ok(Self { renderer, meshes })
+        unsafe { self.renderer.draw(&self.camera, &self.objects) }
+    }
+
+    #[inline(always)]
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.renderer.resize(ash::vk::Extent2D { width, height });
+    }
+
+    #[inline(always)]
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Camera the next frames are drawn from, e.g. to move it or to switch its projection.
+    #[inline(always)]
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    /// Builds a graphics pipeline and registers it under `name`, replacing the one registered before.
+    ///
+    /// Its shaders draw indexed meshes: the mesh vertex layout, the scene uniforms at set 0,
+    /// the texture at set 1 and the model matrix pushed at offset 0, whichever of them they declare.
+    #[inline(always)]
+    pub fn register_pipeline(
+        &mut self,
+        name: &str,
+        builder: PipelineBuilder,
+    ) -> track::Result<PipelineId> {
+        unsafe { self.renderer.register_pipeline(name, builder) }
+    }
+
+    #[inline(always)]
+    pub fn pipeline_id(&self, name: &str) -> Option<PipelineId> {
+        self.renderer.pipeline_id(name)
+    }
+
+    /// Creates the compute pipeline of the shader, replacing the one created before.
+    #[inline(always)]
+    pub fn register_compute_pipeline(
+        &mut self,
+        shader_name: &str,
+    ) -> track::Result<ComputePipelineId> {
+        unsafe { self.renderer.register_compute_pipeline(shader_name) }
+    }
+
+    #[inline(always)]
+    pub fn compute_pipeline_id(&self, shader_name: &str) -> Option<ComputePipelineId> {
+        self.renderer.compute_pipeline_id(shader_name)
+    }
+
+    /// Queues the dispatch to run before the next frame is rendered, fails if it doesn't match
+    /// the bindings or push constants of the shader.
+    #[inline(always)]
+    pub fn dispatch(&mut self, dispatch: ComputeDispatch) -> track::Result<()> {
+        self.renderer.dispatch(dispatch)
+    }
+
+    #[inline(always)]
+    pub fn create_storage_buffer(&mut self, size: u64) -> track::Result<StorageBufferHandle> {
+        self.renderer.create_storage_buffer(size)
+    }
+
+    #[inline(always)]
+    pub fn create_storage_image(
+        &mut self,
+        width: u32,
+        height: u32,
+        format: ash::vk::Format,
+    ) -> track::Result<StorageImageHandle> {
+        self.renderer
+            .create_storage_image(ash::vk::Extent2D { width, height }, format)
+    }
+
+    #[inline(always)]
+    pub fn release_storage_buffer(&mut self, storage_buffer_handle: StorageBufferHandle) {
+        self.renderer.release_storage_buffer(storage_buffer_handle);
+    }
+
+    #[inline(always)]
+    pub fn release_storage_image(&mut self, storage_image_handle: StorageImageHandle) {
+        self.renderer.release_storage_image(storage_image_handle);
     }
 
     #[inline(always)]
-    pub fn draw(&self) -> VkResult<()> {
-        unsafe { self.renderer.draw(&self.meshes[0]) }
+    pub fn capture<P: AsRef<std::path::Path>>(&self, path: P) -> track::Result<()> {
+        unsafe { self.renderer.capture(path) }
     }
 }
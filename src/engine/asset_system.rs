@@ -1,3 +1,462 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use smallvec::smallvec;
+use tracing::info;
+use track::Context;
+
+use self::{
+    handle::RawId,
+    registry::{AssetKey, Registry},
+};
+use super::{
+    renderer::{MeshHandle, RenderObject, Renderer, TextureHandle},
+    utils::paths,
+};
+
+pub(crate) mod binary;
+pub mod handle;
 pub mod mesh;
+pub mod model;
+mod registry;
+pub mod texture;
+
+pub use self::handle::{AssetId, Handle};
+
+/// Asset type stored by the [`AssetSystem`], parsed from a file and uploaded to the GPU.
+pub trait Asset: Sized + 'static {
+    /// What the file is parsed into, on a worker thread for asynchronous loads.
+    type Source: Send + 'static;
+
+    /// Extension appended to the source file name by the cooker.
+    const COOKED_EXTENSION: &'static str;
+
+    /// Parses the source file, or the cooked one in shipping builds.
+    fn parse(path: &Path) -> track::Result<Self::Source>;
+
+    /// Files besides its own the source was parsed from, e.g. the textures of a model.
+    #[inline(always)]
+    fn dependencies(_source: &Self::Source) -> &[PathBuf] {
+        &[]
+    }
+
+    /// Uploads the source and waits for the GPU.
+    fn upload(source: Self::Source, renderer: &mut Renderer) -> track::Result<Self>;
+
+    /// Starts streaming the source without waiting, see [`Renderer::begin_upload`].
+    fn begin_upload(source: Self::Source, renderer: &mut Renderer) -> track::Result<Self>;
+
+    /// Schedules the GPU data for destruction.
+    fn release(&self, renderer: &mut Renderer);
+}
+
+/// Where an asset is on its way from the file to the GPU.
+pub enum AssetState<T: Asset> {
+    /// Being parsed on a worker thread.
+    Loading,
+    /// Parsed and waiting for its upload.
+    Queued(T::Source),
+    /// Being streamed to the GPU.
+    Uploading(T),
+    /// Ready for drawing.
+    Resident(T),
+    Failed,
+}
+
+impl<T: Asset> AssetState<T> {
+    #[inline(always)]
+    pub fn resident(&self) -> Option<&T> {
+        match self {
+            Self::Resident(asset) => Some(asset),
+            _ => None,
+        }
+    }
+}
+
+/// Model with its meshes and textures uploaded to the GPU.
+pub struct ModelAsset {
+    pub model: model::Model,
+    /// GPU meshes in the order of [`model::Model::meshes`].
+    pub mesh_handles: Vec<MeshHandle>,
+    /// GPU textures in the order of [`model::Model::textures`].
+    pub texture_handles: Vec<TextureHandle>,
+}
+
+impl ModelAsset {
+    /// An object for each instance of the model, placed by `transform`.
+    pub fn render_objects(&self, transform: math::Mat4) -> impl Iterator<Item = RenderObject> + '_ {
+        self.model
+            .instances
+            .iter()
+            .map(move |instance| RenderObject {
+                mesh_handle: self.mesh_handles[instance.mesh],
+                texture_handle: instance
+                    .material
+                    .and_then(|material| self.model.materials[material].base_color_texture)
+                    .map(|texture| self.texture_handles[texture]),
+                pipeline: None,
+                transform: transform * instance.transform,
+            })
+    }
+}
+
+impl Asset for ModelAsset {
+    type Source = model::Model;
+
+    const COOKED_EXTENSION: &'static str = model::cache::EXTENSION;
+
+    #[inline(always)]
+    fn parse(path: &Path) -> track::Result<Self::Source> {
+        // NOTE: Cooked models embed what they reference, their recorded dependencies are paths
+        // on the machine that cooked them.
+        #[cfg(feature = "shipping")]
+        return model::cache::read(path, None)
+            .track()?
+            .map(|mut model| {
+                model.dependencies.clear();
+                model
+            })
+            .ok_or(StaleCookedAsset)
+            .track();
+
+        #[cfg(not(feature = "shipping"))]
+        model::Model::load_cached(path, &paths::cache_dir())
+    }
+
+    #[inline(always)]
+    fn dependencies(model: &Self::Source) -> &[PathBuf] {
+        &model.dependencies
+    }
+
+    fn upload(model: Self::Source, renderer: &mut Renderer) -> track::Result<Self> {
+        let mesh_handles = renderer.upload_meshes(&model.meshes).track()?;
+        let texture_handles = renderer.upload_textures(&model.textures).track()?;
+
+        Ok(Self {
+            model,
+            mesh_handles,
+            texture_handles,
+        })
+    }
+
+    fn begin_upload(model: Self::Source, renderer: &mut Renderer) -> track::Result<Self> {
+        let (mesh_handles, texture_handles) = renderer
+            .begin_upload(&model.meshes, &model.textures)
+            .track()?;
+
+        Ok(Self {
+            model,
+            mesh_handles,
+            texture_handles,
+        })
+    }
+
+    fn release(&self, renderer: &mut Renderer) {
+        self.mesh_handles
+            .iter()
+            .for_each(|&mesh_handle| renderer.release_mesh(mesh_handle));
+        self.texture_handles
+            .iter()
+            .for_each(|&texture_handle| renderer.release_texture(texture_handle));
+    }
+}
+
+/// Texture with its pixels uploaded to the GPU.
+pub struct TextureAsset {
+    pub texture: texture::Texture,
+    pub texture_handle: TextureHandle,
+}
+
+impl Asset for TextureAsset {
+    type Source = texture::Texture;
+
+    const COOKED_EXTENSION: &'static str = texture::cache::EXTENSION;
+
+    #[inline(always)]
+    fn parse(path: &Path) -> track::Result<Self::Source> {
+        #[cfg(feature = "shipping")]
+        return texture::cache::read(path, None)
+            .track()?
+            .ok_or(StaleCookedAsset)
+            .track();
+
+        #[cfg(not(feature = "shipping"))]
+        texture::Texture::new(path)
+    }
+
+    fn upload(texture: Self::Source, renderer: &mut Renderer) -> track::Result<Self> {
+        let texture_handle = renderer
+            .upload_textures(std::slice::from_ref(&texture))
+            .track()?[0];
+
+        Ok(Self {
+            texture,
+            texture_handle,
+        })
+    }
+
+    fn begin_upload(texture: Self::Source, renderer: &mut Renderer) -> track::Result<Self> {
+        let (_, texture_handles) = renderer
+            .begin_upload(&[], std::slice::from_ref(&texture))
+            .track()?;
+
+        Ok(Self {
+            texture,
+            texture_handle: texture_handles[0],
+        })
+    }
+
+    #[inline(always)]
+    fn release(&self, renderer: &mut Renderer) {
+        renderer.release_texture(self.texture_handle);
+    }
+}
+
+/// Cooked asset written by another version of the cooker.
+#[derive(Debug)]
+pub struct StaleCookedAsset;
+
+impl std::fmt::Display for StaleCookedAsset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cooked asset is stale, cook the assets again")
+    }
+}
+
+impl std::error::Error for StaleCookedAsset {}
+
+/// Asset whose upload is in the renderer's upload stream.
+enum StreamedUpload {
+    Model(RawId),
+    Texture(RawId),
+}
+
+/// Loads every asset once and keeps it, both on the CPU and the GPU, while there are handles to it.
+///
+/// Assets are deduplicated by their canonical path and, for synchronous loads, by the hash
+/// of the file content, they are freed by [`AssetSystem::update`] after their last handle is dropped.
+///
+/// Asynchronous loads parse files on the rayon thread pool and stream the uploads one at a time,
+/// until then their objects are drawn with the placeholder assets.
+#[derive(Default)]
+pub struct AssetSystem {
+    models: Registry<ModelAsset>,
+    textures: Registry<TextureAsset>,
+    streamed_upload: Option<StreamedUpload>,
+    placeholder_model: Option<Handle<ModelAsset>>,
+    placeholder_texture: Option<Handle<TextureAsset>>,
+}
+
+impl AssetSystem {
+    /// Loads the model and waits until it's resident, unless the same model is being loaded asynchronously.
+    #[inline(always)]
+    pub fn load_model<P: AsRef<Path>>(
+        &mut self,
+        renderer: &mut Renderer,
+        path: P,
+    ) -> track::Result<Handle<ModelAsset>> {
+        load(&mut self.models, path.as_ref(), Some(renderer))
+    }
+
+    /// Same as [`AssetSystem::load_model`] for a texture.
+    #[inline(always)]
+    pub fn load_texture<P: AsRef<Path>>(
+        &mut self,
+        renderer: &mut Renderer,
+        path: P,
+    ) -> track::Result<Handle<TextureAsset>> {
+        load(&mut self.textures, path.as_ref(), Some(renderer))
+    }
+
+    /// Starts loading the model in the background and returns right away, see [`AssetSystem::update`].
+    #[inline(always)]
+    pub fn load_model_async<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> track::Result<Handle<ModelAsset>> {
+        load(&mut self.models, path.as_ref(), None)
+    }
+
+    /// Same as [`AssetSystem::load_model_async`] for a texture.
+    #[inline(always)]
+    pub fn load_texture_async<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> track::Result<Handle<TextureAsset>> {
+        load(&mut self.textures, path.as_ref(), None)
+    }
+
+    /// Sets what is drawn instead of models that aren't resident yet and of untextured meshes.
+    ///
+    /// Placeholders are expected to be loaded synchronously.
+    #[inline]
+    pub fn set_placeholders(
+        &mut self,
+        placeholder_model: Handle<ModelAsset>,
+        placeholder_texture: Handle<TextureAsset>,
+    ) {
+        self.placeholder_model = Some(placeholder_model);
+        self.placeholder_texture = Some(placeholder_texture);
+    }
+
+    /// Objects of the model placed by `transform`, or of the placeholder model while it isn't resident.
+    ///
+    /// Meshes without a texture get the placeholder texture.
+    pub fn render_objects(
+        &self,
+        handle: &Handle<ModelAsset>,
+        transform: math::Mat4,
+    ) -> impl Iterator<Item = RenderObject> + '_ {
+        let model_asset = self.models.get(handle).resident().or_else(|| {
+            self.placeholder_model
+                .as_ref()
+                .and_then(|placeholder_model| self.models.get(placeholder_model).resident())
+        });
+        let placeholder_texture_handle = self
+            .placeholder_texture
+            .as_ref()
+            .and_then(|placeholder_texture| self.textures.get(placeholder_texture).resident())
+            .map(|texture_asset| texture_asset.texture_handle);
+
+        model_asset
+            .into_iter()
+            .flat_map(move |model_asset| model_asset.render_objects(transform))
+            .map(move |mut object| {
+                object.texture_handle = object.texture_handle.or(placeholder_texture_handle);
+
+                object
+            })
+    }
+
+    /// Frees the assets whose last handle was dropped, queues the freshly parsed ones
+    /// and streams them to the GPU one after another. Meant to be called once per frame.
+    pub fn update(&mut self, renderer: &mut Renderer) -> track::Result<()> {
+        self.collect_garbage(renderer);
+
+        self.models.receive_parsed();
+        self.textures.receive_parsed();
+
+        renderer.poll_upload().track()?;
+        if renderer.is_uploading() {
+            return Ok(());
+        }
+
+        // NOTE: A synchronous load may have waited for the streamed upload already.
+        match self.streamed_upload.take() {
+            Some(StreamedUpload::Model(raw)) => self.models.finish_upload(raw),
+            Some(StreamedUpload::Texture(raw)) => self.textures.finish_upload(raw),
+            None => (),
+        }
+
+        self.streamed_upload = match self.models.begin_next_upload(renderer).track()? {
+            Some(raw) => Some(StreamedUpload::Model(raw)),
+            None => self
+                .textures
+                .begin_next_upload(renderer)
+                .track()?
+                .map(StreamedUpload::Texture),
+        };
+
+        Ok(())
+    }
+
+    fn collect_garbage(&mut self, renderer: &mut Renderer) {
+        let models = self.models.collect_garbage();
+        let textures = self.textures.collect_garbage();
+
+        if models.is_empty() && textures.is_empty() {
+            return;
+        }
+
+        models.iter().for_each(|state| release(state, renderer));
+        textures.iter().for_each(|state| release(state, renderer));
+
+        info!(
+            "Freed {} models and {} textures, {} models and {} textures left",
+            models.len(),
+            textures.len(),
+            self.models.len(),
+            self.textures.len()
+        );
+    }
+}
+
+#[inline(always)]
+fn release<T: Asset>(state: &AssetState<T>, renderer: &mut Renderer) {
+    if let AssetState::Uploading(asset) | AssetState::Resident(asset) = state {
+        asset.release(renderer);
+    }
+}
+
+/// Returns the asset known by the path or the content of the file, loading it only if there is none.
+///
+/// Without a renderer the file is parsed on the rayon thread pool and queued for a streamed upload.
+/// Such loads are only deduplicated by the path, as hashing the content would read the whole file
+/// on the caller's thread.
+/// Shipping builds load the cooked file of the source instead.
+fn load<T: Asset>(
+    registry: &mut Registry<T>,
+    path: &Path,
+    renderer: Option<&mut Renderer>,
+) -> track::Result<Handle<T>> {
+    // NOTE: Shipping builds never touch the source assets, only their cooked versions.
+    #[cfg(feature = "shipping")]
+    let path = &paths::cooked_path(
+        &paths::assets_dir(),
+        &paths::cooked_dir(),
+        path,
+        T::COOKED_EXTENSION,
+    );
+
+    let path_key = AssetKey::Path(path.canonicalize().track()?);
+    if let Some(handle) = registry.find(&path_key) {
+        return Ok(handle);
+    }
+
+    let Some(renderer) = renderer else {
+        info!("Loading {path:?}");
+        let handle = registry.insert(smallvec![path_key], AssetState::Loading);
+
+        let raw = handle.id().raw();
+        let parsed_sender = registry.parsed_sender();
+        let path = PathBuf::from(path);
+        rayon::spawn(move || {
+            // NOTE: The registry may be gone by now, then nobody waits for the asset.
+            let _ = parsed_sender.send((raw, T::parse(&path)));
+        });
+
+        return Ok(handle);
+    };
+
+    // NOTE: The files the source references are only known once it's parsed,
+    // so content duplicates are parsed but never uploaded.
+    let source = T::parse(path).track()?;
+    let content_key = AssetKey::ContentHash(content_hash(path, T::dependencies(&source)).track()?);
+    if let Some(handle) = registry.find(&content_key) {
+        registry.alias(path_key, &handle);
+
+        return Ok(handle);
+    }
+
+    info!("Loading {path:?}");
+    let asset = T::upload(source, renderer).track()?;
+
+    Ok(registry.insert(
+        smallvec![path_key, content_key],
+        AssetState::Resident(asset),
+    ))
+}
+
+/// Hash of the file together with the files it references, so equal files next to different
+/// materials, textures or buffers stay separate assets.
+fn content_hash(path: &Path, dependencies: &[PathBuf]) -> track::Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    std::fs::read(path).track()?.hash(&mut hasher);
+    for dependency in dependencies {
+        std::fs::read(dependency).track()?.hash(&mut hasher);
+    }
 
-pub struct AssetSystem {}
+    Ok(hasher.finish())
+}
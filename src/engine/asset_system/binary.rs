@@ -0,0 +1,359 @@
+//! Building blocks of the engine's binary asset formats, shared by caches and cooked assets.
+//!
+//! Every file starts with a header of a 4-byte magic, a version, flags and the stamp of the source
+//! file it was written from. Values are little-endian and follow each other without padding.
+
+use std::{
+    borrow::Cow,
+    fmt,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use track::Context;
+
+const FLAG_LZ4: u32 = 1;
+const NO_INDEX: i32 = -1;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    #[default]
+    Lz4,
+}
+
+/// Identifies the version of the source file a binary asset was written from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceStamp {
+    pub len: u64,
+    /// Nanoseconds since the Unix epoch.
+    pub modified: u64,
+}
+
+impl SourceStamp {
+    pub fn new(source_path: &Path) -> track::Result<Self> {
+        let metadata = fs::metadata(source_path).track()?;
+        let modified = metadata
+            .modified()
+            .track()?
+            .duration_since(UNIX_EPOCH)
+            .track()?
+            .as_nanos() as u64;
+
+        Ok(Self {
+            len: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// Binary asset that ends before its content does.
+#[derive(Debug)]
+pub struct TruncatedFile;
+
+impl fmt::Display for TruncatedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Binary asset is truncated")
+    }
+}
+
+impl std::error::Error for TruncatedFile {}
+
+/// Memory-maps the file, returns `None` if it doesn't exist.
+pub fn map(path: &Path) -> track::Result<Option<memmap2::Mmap>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error).track(),
+    };
+
+    // SAFETY: Binary assets are only replaced by renaming, never modified in place.
+    unsafe { memmap2::Mmap::map(&file).map(Some).track() }
+}
+
+/// Writes the bytes next to a temporary file and renames it over `path`,
+/// so readers never see a half-written file.
+pub fn write_file(path: &Path, bytes: &[u8]) -> track::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).track()?;
+    }
+
+    let temporary_path = path.with_extension("tmp");
+    fs::write(&temporary_path, bytes).track()?;
+    fs::rename(&temporary_path, path).track()
+}
+
+pub struct Writer {
+    pub bytes: Vec<u8>,
+    compression: Compression,
+}
+
+impl Writer {
+    /// Starts a file with its header.
+    pub fn new(
+        magic: [u8; 4],
+        version: u32,
+        source_stamp: SourceStamp,
+        compression: Compression,
+    ) -> Self {
+        let mut writer = Self {
+            bytes: Vec::new(),
+            compression,
+        };
+
+        writer.bytes.extend_from_slice(&magic);
+        writer.u32(version);
+        writer.u32(match compression {
+            Compression::None => 0,
+            Compression::Lz4 => FLAG_LZ4,
+        });
+        writer.stamp(source_stamp);
+
+        writer
+    }
+
+    #[inline(always)]
+    pub fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    #[inline(always)]
+    pub fn i32(&mut self, value: i32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    #[inline(always)]
+    pub fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    #[inline(always)]
+    pub fn f32s(&mut self, values: &[f32]) {
+        values
+            .iter()
+            .for_each(|value| self.bytes.extend_from_slice(&value.to_le_bytes()));
+    }
+
+    #[inline(always)]
+    pub fn optional_index(&mut self, index: Option<usize>) {
+        self.i32(index.map_or(NO_INDEX, |index| index as i32));
+    }
+
+    #[inline(always)]
+    pub fn stamp(&mut self, stamp: SourceStamp) {
+        self.u64(stamp.len);
+        self.u64(stamp.modified);
+    }
+
+    /// Writes the path as UTF-8 prefixed by its length, never compressed.
+    pub fn path(&mut self, path: &Path) {
+        let path = path.to_string_lossy();
+
+        self.u32(path.len() as u32);
+        self.bytes.extend_from_slice(path.as_bytes());
+    }
+
+    /// Writes the data prefixed by its size, compressed if the file is.
+    pub fn payload(&mut self, data: &[u8]) {
+        let data = match self.compression {
+            Compression::None => Cow::Borrowed(data),
+            Compression::Lz4 => Cow::Owned(lz4_flex::compress_prepend_size(data)),
+        };
+
+        self.u64(data.len() as u64);
+        self.bytes.extend_from_slice(&data);
+    }
+}
+
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    compression: Compression,
+}
+
+impl<'a> Reader<'a> {
+    /// Reads the header, returns `None` if the magic or version don't match or the file was written
+    /// from another version of the source. Without `source_stamp` any source is accepted.
+    pub fn new(
+        bytes: &'a [u8],
+        magic: [u8; 4],
+        version: u32,
+        source_stamp: Option<SourceStamp>,
+    ) -> Result<Option<Self>, TruncatedFile> {
+        let mut reader = Self {
+            bytes,
+            compression: Compression::None,
+        };
+
+        if reader.take(magic.len())? != magic || reader.u32()? != version {
+            return Ok(None);
+        }
+
+        let flags = reader.u32()?;
+        let stamp = reader.stamp()?;
+        if source_stamp.is_some_and(|source_stamp| source_stamp != stamp) {
+            return Ok(None);
+        }
+
+        reader.compression = match flags & FLAG_LZ4 {
+            0 => Compression::None,
+            _ => Compression::Lz4,
+        };
+
+        Ok(Some(reader))
+    }
+
+    #[inline(always)]
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], TruncatedFile> {
+        if self.bytes.len() < len {
+            return Err(TruncatedFile);
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    #[inline(always)]
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], TruncatedFile> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    #[inline(always)]
+    pub fn u32(&mut self) -> Result<u32, TruncatedFile> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    #[inline(always)]
+    pub fn i32(&mut self) -> Result<i32, TruncatedFile> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    #[inline(always)]
+    pub fn u64(&mut self) -> Result<u64, TruncatedFile> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    #[inline(always)]
+    pub fn f32s<const N: usize>(&mut self) -> Result<[f32; N], TruncatedFile> {
+        let mut values = [0.0; N];
+        for value in values.iter_mut() {
+            *value = f32::from_le_bytes(self.array()?);
+        }
+
+        Ok(values)
+    }
+
+    #[inline(always)]
+    pub fn optional_index(&mut self) -> Result<Option<usize>, TruncatedFile> {
+        Ok(usize::try_from(self.i32()?).ok())
+    }
+
+    #[inline(always)]
+    pub fn stamp(&mut self) -> Result<SourceStamp, TruncatedFile> {
+        Ok(SourceStamp {
+            len: self.u64()?,
+            modified: self.u64()?,
+        })
+    }
+
+    /// Reads a path written by [`Writer::path`].
+    pub fn path(&mut self) -> Result<PathBuf, TruncatedFile> {
+        let len = self.u32()? as usize;
+        let path = self.take(len)?;
+
+        Ok(PathBuf::from(String::from_utf8_lossy(path).into_owned()))
+    }
+
+    /// Reads data written by [`Writer::payload`].
+    pub fn payload(&mut self) -> track::Result<Vec<u8>> {
+        let len = self.u64().track()? as usize;
+        let data = self.take(len).track()?;
+
+        match self.compression {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Lz4 => lz4_flex::decompress_size_prepended(data).track(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAGIC: [u8; 4] = *b"TEST";
+    const VERSION: u32 = 3;
+    const STAMP: SourceStamp = SourceStamp {
+        len: 42,
+        modified: 7,
+    };
+
+    fn write_sample(compression: Compression) -> Vec<u8> {
+        let mut writer = Writer::new(MAGIC, VERSION, STAMP, compression);
+        writer.u32(3);
+        writer.i32(-5);
+        writer.f32s(&[1.0, 2.5]);
+        writer.optional_index(Some(4));
+        writer.optional_index(None);
+        writer.path(Path::new("models/cube.obj"));
+        writer.stamp(STAMP);
+        writer.payload(b"pixels");
+
+        writer.bytes
+    }
+
+    fn read_sample(bytes: &[u8]) -> track::Result<()> {
+        let mut reader = Reader::new(bytes, MAGIC, VERSION, Some(STAMP))
+            .track()?
+            .unwrap();
+
+        assert_eq!(reader.u32().track()?, 3);
+        assert_eq!(reader.i32().track()?, -5);
+        assert_eq!(reader.f32s::<2>().track()?, [1.0, 2.5]);
+        assert_eq!(reader.optional_index().track()?, Some(4));
+        assert_eq!(reader.optional_index().track()?, None);
+        assert_eq!(reader.path().track()?, Path::new("models/cube.obj"));
+        assert_eq!(reader.stamp().track()?, STAMP);
+        assert_eq!(reader.payload().track()?, b"pixels");
+        assert!(reader.take(1).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() {
+        for compression in [Compression::None, Compression::Lz4] {
+            read_sample(&write_sample(compression)).unwrap();
+        }
+    }
+
+    #[test]
+    fn stale_header() {
+        let bytes = write_sample(Compression::None);
+        let stale_stamp = SourceStamp {
+            modified: 8,
+            ..STAMP
+        };
+
+        assert!(Reader::new(&bytes, *b"NOPE", VERSION, None)
+            .unwrap()
+            .is_none());
+        assert!(Reader::new(&bytes, MAGIC, VERSION + 1, None)
+            .unwrap()
+            .is_none());
+        assert!(Reader::new(&bytes, MAGIC, VERSION, Some(stale_stamp))
+            .unwrap()
+            .is_none());
+        assert!(Reader::new(&bytes, MAGIC, VERSION, None).unwrap().is_some());
+    }
+
+    #[test]
+    fn truncated() {
+        for compression in [Compression::None, Compression::Lz4] {
+            let bytes = write_sample(compression);
+
+            (0..bytes.len()).for_each(|len| assert!(read_sample(&bytes[..len]).is_err()));
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    sync::{mpsc::Sender, Arc},
+};
+
+/// Untyped slot of an asset, the generation tells apart assets that reused the slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RawId {
+    pub index: u32,
+    pub generation: u32,
+}
+
+/// Weak typed reference to an asset, it doesn't keep the asset alive.
+pub struct AssetId<T> {
+    raw: RawId,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> AssetId<T> {
+    #[inline(always)]
+    pub(super) fn new(raw: RawId) -> Self {
+        Self {
+            raw,
+            marker: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn raw(&self) -> RawId {
+        self.raw
+    }
+}
+
+// NOTE: Implemented by hand, derives would require `T` itself to implement the traits.
+impl<T> Clone for AssetId<T> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for AssetId<T> {}
+
+impl<T> PartialEq for AssetId<T> {
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<T> Eq for AssetId<T> {}
+
+impl<T> Hash for AssetId<T> {
+    #[inline(always)]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for AssetId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "AssetId<{}>({}v{})",
+            std::any::type_name::<T>(),
+            self.raw.index,
+            self.raw.generation
+        )
+    }
+}
+
+/// Notifies the registry once the last handle of an asset is dropped.
+pub(super) struct DropGuard {
+    raw: RawId,
+    drop_sender: Sender<RawId>,
+}
+
+impl DropGuard {
+    #[inline(always)]
+    pub(super) fn new(raw: RawId, drop_sender: Sender<RawId>) -> Self {
+        Self { raw, drop_sender }
+    }
+}
+
+impl Drop for DropGuard {
+    fn drop(&mut self) {
+        // NOTE: The registry may already be gone, then there is nothing to free.
+        let _ = self.drop_sender.send(self.raw);
+    }
+}
+
+/// Strong typed reference to an asset, the asset is freed once its last handle is dropped
+/// and the owning registry collects the garbage.
+pub struct Handle<T> {
+    id: AssetId<T>,
+    guard: Arc<DropGuard>,
+}
+
+impl<T> Handle<T> {
+    #[inline(always)]
+    pub(super) fn new(id: AssetId<T>, guard: Arc<DropGuard>) -> Self {
+        Self { id, guard }
+    }
+
+    #[inline(always)]
+    pub fn id(&self) -> AssetId<T> {
+        self.id
+    }
+
+    /// Count of handles referencing the asset, this one included.
+    #[inline(always)]
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.guard)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            guard: Arc::clone(&self.guard),
+        }
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    #[inline(always)]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("id", &self.id)
+            .field("ref_count", &self.ref_count())
+            .finish()
+    }
+}
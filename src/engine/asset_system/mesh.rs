@@ -1,18 +1,21 @@
-use std::mem;
-use std::path::Path;
+use std::{collections::HashMap, fmt, mem};
 
 use ash::vk;
-use math::Vec3;
+use math::{Vec2, Vec3, Vec4};
 use memoffset::offset_of;
 use rayon::prelude::*;
-use track::Context;
 
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct Vertex {
     pub position: Vec3,
     pub color: Vec3,
+    pub uv: Vec2,
+    /// Tangent with the handedness of the bitangent in `w`, zeroed for meshes without texture coordinates.
+    pub tangent: Vec4,
 }
 
+#[derive(Clone)]
 pub struct VertexDescription {
     pub binding: vk::VertexInputBindingDescription,
     pub attributes: Vec<vk::VertexInputAttributeDescription>,
@@ -21,7 +24,9 @@ pub struct VertexDescription {
 // TODO: Give a different vertex input description depending on type of resources.
 impl VertexDescription {
     const VERTEX_FORMAT: vk::Format = vk::Format::R32G32B32_SFLOAT;
-    const MESH_ATTRIBUTES_LENGTH: usize = 3;
+    const UV_FORMAT: vk::Format = vk::Format::R32G32_SFLOAT;
+    const TANGENT_FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
+    const MESH_ATTRIBUTES_LENGTH: usize = 4;
 
     #[inline]
     pub fn new() -> Self {
@@ -44,6 +49,18 @@ impl VertexDescription {
             Self::VERTEX_FORMAT,
             &mut location,
         ));
+        attributes.push(Self::create_attribute(
+            Default::default(),
+            offset_of!(Vertex, uv) as u32,
+            Self::UV_FORMAT,
+            &mut location,
+        ));
+        attributes.push(Self::create_attribute(
+            Default::default(),
+            offset_of!(Vertex, tangent) as u32,
+            Self::TANGENT_FORMAT,
+            &mut location,
+        ));
 
         Self {
             binding,
@@ -70,6 +87,82 @@ impl VertexDescription {
     }
 }
 
+/// Malformed mesh data found while loading a model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MeshError {
+    /// The mesh has no triangles.
+    Empty,
+    /// The length of an attribute array isn't a multiple of its component count.
+    MalformedAttribute {
+        attribute: &'static str,
+        len: usize,
+        components: usize,
+    },
+    /// An attribute has a different element count than the positions.
+    MismatchedAttribute {
+        attribute: &'static str,
+        count: usize,
+        vertex_count: usize,
+    },
+    /// The index count isn't a multiple of 3.
+    IncompleteTriangle {
+        index_count: usize,
+    },
+    IndexOutOfBounds {
+        index: u32,
+        vertex_count: usize,
+    },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Mesh has no triangles"),
+            Self::MalformedAttribute {
+                attribute,
+                len,
+                components,
+            } => write!(
+                f,
+                "Mesh {attribute} have {len} components, not a multiple of {components}"
+            ),
+            Self::MismatchedAttribute {
+                attribute,
+                count,
+                vertex_count,
+            } => write!(
+                f,
+                "Mesh has {count} {attribute} for {vertex_count} positions"
+            ),
+            Self::IncompleteTriangle { index_count } => write!(
+                f,
+                "Mesh has {index_count} indices, not a multiple of {}",
+                Mesh::TRIANGLE_VERTEX_COUNT
+            ),
+            Self::IndexOutOfBounds {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "Mesh index {index} is out of bounds of {vertex_count} vertices"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// How normals are computed for meshes that don't have them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalGeneration {
+    /// Vertices at the same position share a normal averaged from the faces around it,
+    /// weighted by the angle of each face at the vertex.
+    #[default]
+    Smooth,
+    /// Every triangle gets its own vertices with the face normal.
+    Flat,
+}
+
 pub struct Mesh {
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
@@ -77,34 +170,287 @@ pub struct Mesh {
 
 impl Mesh {
     pub const TRIANGLE_VERTEX_COUNT: usize = 3;
+    pub const UV_COMPONENTS_COUNT: usize = 2;
 
-    pub fn new<P: AsRef<Path> + std::fmt::Debug>(path: P) -> track::Result<Self> {
-        let (mut models, _) = tobj::load_obj(
-            path,
-            &tobj::LoadOptions {
-                triangulate: true,
-                ignore_points: true,
-                ignore_lines: true,
-                single_index: true,
-            },
-        )
-        .track()?;
+    /// Converts a mesh loaded by `tobj` with a single index, normals become vertex colors.
+    ///
+    /// Missing normals are generated, tangents are generated when texture coordinates exist.
+    pub fn from_obj(
+        mesh: tobj::Mesh,
+        normal_generation: NormalGeneration,
+    ) -> Result<Self, MeshError> {
+        let vertex_count = attribute_count(
+            "positions",
+            mesh.positions.len(),
+            Self::TRIANGLE_VERTEX_COUNT,
+        )?;
+
+        let has_normals = !mesh.normals.is_empty();
+        if has_normals {
+            check_attribute(
+                "normals",
+                attribute_count("normals", mesh.normals.len(), Self::TRIANGLE_VERTEX_COUNT)?,
+                vertex_count,
+            )?;
+        }
 
-        let mesh = models.remove(Default::default()).mesh;
+        let has_uvs = !mesh.texcoords.is_empty();
+        if has_uvs {
+            check_attribute(
+                "texture coordinates",
+                attribute_count(
+                    "texture coordinates",
+                    mesh.texcoords.len(),
+                    Self::UV_COMPONENTS_COUNT,
+                )?,
+                vertex_count,
+            )?;
+        }
 
         let vertices: Vec<Vertex> = mesh
             .positions
             .par_chunks_exact(Self::TRIANGLE_VERTEX_COUNT)
-            .zip(mesh.normals.par_chunks_exact(Self::TRIANGLE_VERTEX_COUNT))
-            .map(|(position, normal)| Vertex {
+            .enumerate()
+            .map(|(index, position)| Vertex {
                 position: Vec3::from_row_slice(position),
-                color: Vec3::from_row_slice(normal),
+                color: mesh
+                    .normals
+                    .get(
+                        index * Self::TRIANGLE_VERTEX_COUNT
+                            ..(index + 1) * Self::TRIANGLE_VERTEX_COUNT,
+                    )
+                    .map_or_else(Vec3::zeros, Vec3::from_row_slice),
+                // OBJ puts the origin of texture coordinates at the bottom left corner, Vulkan at the top left one.
+                uv: mesh
+                    .texcoords
+                    .get(index * Self::UV_COMPONENTS_COUNT..(index + 1) * Self::UV_COMPONENTS_COUNT)
+                    .map_or_else(Vec2::zeros, |uv| Vec2::new(uv[0], 1.0 - uv[1])),
+                tangent: Vec4::zeros(),
             })
             .collect();
 
-        Ok(Self {
+        Self::with_generated_attributes(
             vertices,
-            indices: mesh.indices,
+            mesh.indices,
+            (!has_normals).then_some(normal_generation),
+            has_uvs,
+        )
+    }
+
+    /// Validates the mesh and generates its normals with `normal_generation` if it's given
+    /// and its tangents if `generate_tangents` is set.
+    pub fn with_generated_attributes(
+        vertices: Vec<Vertex>,
+        indices: Vec<u32>,
+        normal_generation: Option<NormalGeneration>,
+        generate_tangents: bool,
+    ) -> Result<Self, MeshError> {
+        let mut mesh = Self { vertices, indices };
+        mesh.validate()?;
+
+        if let Some(normal_generation) = normal_generation {
+            mesh.generate_normals(normal_generation);
+        }
+
+        if generate_tangents {
+            mesh.generate_tangents();
+        }
+
+        Ok(mesh)
+    }
+
+    pub fn validate(&self) -> Result<(), MeshError> {
+        if self.indices.is_empty() {
+            return Err(MeshError::Empty);
+        }
+
+        if self.indices.len() % Self::TRIANGLE_VERTEX_COUNT != 0 {
+            return Err(MeshError::IncompleteTriangle {
+                index_count: self.indices.len(),
+            });
+        }
+
+        match self
+            .indices
+            .iter()
+            .find(|&&index| index as usize >= self.vertices.len())
+        {
+            Some(&index) => Err(MeshError::IndexOutOfBounds {
+                index,
+                vertex_count: self.vertices.len(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Axis-aligned bounding box of the vertices as `(min, max)`, zeroed for a mesh without vertices.
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        let mut vertices = self.vertices.iter();
+        let Some(first) = vertices.next() else {
+            return (Vec3::zeros(), Vec3::zeros());
+        };
+
+        vertices.fold((first.position, first.position), |(min, max), vertex| {
+            (min.inf(&vertex.position), max.sup(&vertex.position))
         })
     }
+
+    /// Overwrites the normals, which are stored as vertex colors. Expects a validated mesh.
+    pub fn generate_normals(&mut self, normal_generation: NormalGeneration) {
+        match normal_generation {
+            NormalGeneration::Flat => {
+                let vertices: Vec<Vertex> = self
+                    .indices
+                    .chunks_exact(Self::TRIANGLE_VERTEX_COUNT)
+                    .flat_map(|triangle| {
+                        let mut vertices = [
+                            self.vertices[triangle[0] as usize],
+                            self.vertices[triangle[1] as usize],
+                            self.vertices[triangle[2] as usize],
+                        ];
+                        let normal = face_normal(vertices.map(|vertex| vertex.position));
+                        vertices.iter_mut().for_each(|vertex| vertex.color = normal);
+
+                        vertices
+                    })
+                    .collect();
+
+                self.indices = (0..vertices.len() as u32).collect();
+                self.vertices = vertices;
+            }
+            NormalGeneration::Smooth => {
+                // NOTE: Keyed by position instead of index, so seams of texture coordinates stay smooth.
+                let mut normals: HashMap<[u32; 3], Vec3> = HashMap::new();
+                let position_key =
+                    |position: Vec3| -> [u32; 3] { position.map(f32::to_bits).into() };
+
+                self.indices
+                    .chunks_exact(Self::TRIANGLE_VERTEX_COUNT)
+                    .for_each(|triangle| {
+                        let positions = [
+                            self.vertices[triangle[0] as usize].position,
+                            self.vertices[triangle[1] as usize].position,
+                            self.vertices[triangle[2] as usize].position,
+                        ];
+                        let normal = face_normal(positions);
+
+                        (0..Self::TRIANGLE_VERTEX_COUNT).for_each(|corner| {
+                            let position = positions[corner];
+                            let to_next =
+                                positions[(corner + 1) % Self::TRIANGLE_VERTEX_COUNT] - position;
+                            let to_previous = positions[(corner + Self::TRIANGLE_VERTEX_COUNT - 1)
+                                % Self::TRIANGLE_VERTEX_COUNT]
+                                - position;
+
+                            let angle = math::angle(&to_next, &to_previous);
+                            if angle.is_finite() {
+                                *normals
+                                    .entry(position_key(position))
+                                    .or_insert_with(Vec3::zeros) += normal * angle;
+                            }
+                        });
+                    });
+
+                self.vertices.iter_mut().for_each(|vertex| {
+                    vertex.color = normals
+                        .get(&position_key(vertex.position))
+                        .map_or_else(Vec3::zeros, |normal| normal.normalize());
+                });
+            }
+        }
+    }
+
+    /// Computes per-vertex tangents from the texture coordinates, orthogonalized against the normals.
+    /// Expects a validated mesh.
+    pub fn generate_tangents(&mut self) {
+        let mut tangents = vec![Vec3::zeros(); self.vertices.len()];
+        let mut bitangents = vec![Vec3::zeros(); self.vertices.len()];
+
+        self.indices
+            .chunks_exact(Self::TRIANGLE_VERTEX_COUNT)
+            .for_each(|triangle| {
+                let [v0, v1, v2] = [triangle[0], triangle[1], triangle[2]]
+                    .map(|index| &self.vertices[index as usize]);
+
+                let edge1 = v1.position - v0.position;
+                let edge2 = v2.position - v0.position;
+                let delta_uv1 = v1.uv - v0.uv;
+                let delta_uv2 = v2.uv - v0.uv;
+
+                let determinant = delta_uv1.x * delta_uv2.y - delta_uv2.x * delta_uv1.y;
+                if determinant.abs() <= f32::EPSILON {
+                    return;
+                }
+
+                let tangent = (edge1 * delta_uv2.y - edge2 * delta_uv1.y) / determinant;
+                let bitangent = (edge2 * delta_uv1.x - edge1 * delta_uv2.x) / determinant;
+
+                triangle.iter().for_each(|&index| {
+                    tangents[index as usize] += tangent;
+                    bitangents[index as usize] += bitangent;
+                });
+            });
+
+        self.vertices
+            .iter_mut()
+            .zip(tangents.iter().zip(bitangents.iter()))
+            .for_each(|(vertex, (tangent, bitangent))| {
+                let normal = vertex.color;
+                let tangent = tangent - normal * normal.dot(tangent);
+
+                vertex.tangent = match tangent.try_normalize(f32::EPSILON) {
+                    Some(tangent) => {
+                        let handedness = match normal.cross(&tangent).dot(bitangent) < 0.0 {
+                            true => -1.0,
+                            false => 1.0,
+                        };
+
+                        tangent.push(handedness)
+                    }
+                    None => Vec4::zeros(),
+                };
+            });
+    }
+}
+
+/// Element count of a flat attribute array.
+#[inline(always)]
+fn attribute_count(
+    attribute: &'static str,
+    len: usize,
+    components: usize,
+) -> Result<usize, MeshError> {
+    match len % components {
+        0 => Ok(len / components),
+        _ => Err(MeshError::MalformedAttribute {
+            attribute,
+            len,
+            components,
+        }),
+    }
+}
+
+#[inline(always)]
+fn check_attribute(
+    attribute: &'static str,
+    count: usize,
+    vertex_count: usize,
+) -> Result<(), MeshError> {
+    match count == vertex_count {
+        true => Ok(()),
+        false => Err(MeshError::MismatchedAttribute {
+            attribute,
+            count,
+            vertex_count,
+        }),
+    }
+}
+
+/// Unit normal of a counter-clockwise triangle, zero for a degenerate one.
+#[inline(always)]
+fn face_normal(positions: [Vec3; 3]) -> Vec3 {
+    (positions[1] - positions[0])
+        .cross(&(positions[2] - positions[0]))
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(Vec3::zeros)
 }
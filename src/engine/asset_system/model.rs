@@ -0,0 +1,135 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use math::{Mat4, Vec3, Vec4};
+use tracing::{info, warn};
+use track::Context;
+
+use super::{
+    mesh::{Mesh, NormalGeneration},
+    texture::Texture,
+};
+
+pub mod cache;
+mod gltf;
+mod obj;
+
+/// Surface parameters shared by the meshes of a model.
+pub struct Material {
+    pub base_color_factor: Vec4,
+    /// Index into [`Model::textures`].
+    pub base_color_texture: Option<usize>,
+    pub specular_color: Vec3,
+    /// Index into [`Model::textures`].
+    pub specular_texture: Option<usize>,
+}
+
+/// Mesh placed into the model's space.
+pub struct MeshInstance {
+    /// Index into [`Model::meshes`].
+    pub mesh: usize,
+    /// Index into [`Model::materials`].
+    pub material: Option<usize>,
+    pub transform: Mat4,
+}
+
+/// Everything loaded from a model file, meshes are referenced by their instances.
+#[derive(Default)]
+pub struct Model {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    pub textures: Vec<Texture>,
+    pub instances: Vec<MeshInstance>,
+    /// Files besides the source the model was loaded from, e.g. textures or material libraries.
+    pub dependencies: Vec<PathBuf>,
+}
+
+impl Model {
+    /// Loads a glTF (`.gltf` or `.glb`) or OBJ model, picked by the extension regardless of its case.
+    ///
+    /// Meshes without normals get smooth ones.
+    #[inline]
+    pub fn new<P: AsRef<Path> + std::fmt::Debug>(path: P) -> track::Result<Self> {
+        Self::with_normal_generation(path, NormalGeneration::Smooth)
+    }
+
+    /// Same as [`Model::new`], generating missing normals with `normal_generation`.
+    pub fn with_normal_generation<P: AsRef<Path> + std::fmt::Debug>(
+        path: P,
+        normal_generation: NormalGeneration,
+    ) -> track::Result<Self> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("gltf" | "glb") => gltf::load(path.as_ref(), normal_generation).track(),
+            Some("obj") => obj::load(path.as_ref(), normal_generation).track(),
+            _ => Err(UnknownModelFormat(path.as_ref().to_owned())).track(),
+        }
+    }
+
+    /// Reads the model from its binary cache in `cache_dir`, parsing the source file and writing
+    /// the cache instead if there is none or the source or any of its dependencies changed since.
+    ///
+    /// A broken cache is only reported, the source is used then.
+    pub fn load_cached<P: AsRef<Path> + std::fmt::Debug>(
+        path: P,
+        cache_dir: &Path,
+    ) -> track::Result<Self> {
+        let source_stamp = cache::SourceStamp::new(path.as_ref()).track()?;
+        let cache_path = Self::cache_path(path.as_ref(), cache_dir).track()?;
+
+        match cache::read(&cache_path, Some(source_stamp)) {
+            Ok(Some(model)) => return Ok(model),
+            Ok(None) => info!("Caching {path:?} into {cache_path:?}"),
+            Err(error) => warn!("Ignoring the broken cache {cache_path:?}: {error}"),
+        }
+
+        let model = Self::new(path.as_ref()).track()?;
+
+        if let Err(error) = cache::write(
+            &cache_path,
+            source_stamp,
+            &model,
+            cache::Compression::default(),
+        ) {
+            warn!("Failed to write the cache {cache_path:?}: {error}");
+        }
+
+        Ok(model)
+    }
+
+    /// Cache file of the model in `cache_dir`, unique for every source file.
+    pub fn cache_path(path: &Path, cache_dir: &Path) -> track::Result<PathBuf> {
+        let mut hasher = DefaultHasher::new();
+        path.canonicalize().track()?.hash(&mut hasher);
+
+        let stem = path
+            .file_stem()
+            .map_or_else(Default::default, |stem| stem.to_string_lossy());
+
+        Ok(cache_dir.join(format!(
+            "{stem}-{:016x}.{}",
+            hasher.finish(),
+            cache::EXTENSION
+        )))
+    }
+}
+
+/// Model file whose extension isn't one of the supported formats.
+#[derive(Debug)]
+pub struct UnknownModelFormat(PathBuf);
+
+impl std::fmt::Display for UnknownModelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown model format: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownModelFormat {}
@@ -0,0 +1,433 @@
+//! Binary model format, written from a parsed source file as a cache or by the cooker,
+//! and memory-mapped when loading.
+//!
+//! After the common header of [`binary`](crate::engine::asset_system::binary) come:
+//!
+//! | Section      | Content                                                                           |
+//! |--------------|-----------------------------------------------------------------------------------|
+//! | Dependencies | count, path and stamp of every file besides the source the model was loaded from  |
+//! | Layout       | vertex stride, attribute count, `(location, format, offset)` of every attribute   |
+//! | Counts       | meshes, materials, textures, instances                                            |
+//! | Meshes       | vertex count, index count, index width, bounds, payload of vertices then indices  |
+//! | Materials    | base color, base color texture, specular color, specular texture (`-1` for none)  |
+//! | Textures     | width, height, payload of RGBA8 pixels                                            |
+//! | Instances    | mesh, material (`-1` for none), column-major transform                            |
+//!
+//! A file whose version, vertex layout or the stamp of its source or of any dependency doesn't match
+//! is stale.
+
+use std::{fmt, path::Path};
+
+use math::{Mat4, Vec3, Vec4};
+use track::Context;
+
+pub use crate::engine::asset_system::binary::{Compression, SourceStamp};
+use crate::engine::{
+    asset_system::{
+        binary::{self, Reader, TruncatedFile, Writer},
+        mesh::{Mesh, Vertex, VertexDescription},
+        texture::Texture,
+    },
+    utils::bytes,
+};
+
+use super::{Material, MeshInstance, Model};
+
+pub const EXTENSION: &str = "vlmodel";
+
+const MAGIC: [u8; 4] = *b"VLMC";
+const VERSION: u32 = 2;
+
+/// Binary model whose content doesn't hold together, e.g. an instance of a mesh it doesn't have.
+#[derive(Debug)]
+pub struct InvalidModel(String);
+
+impl fmt::Display for InvalidModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid binary model: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidModel {}
+
+/// Reads the binary model, returns `None` if there is none or it's stale.
+///
+/// Without `source_stamp` the file is trusted to match its source and dependencies,
+/// e.g. for cooked assets.
+pub fn read(path: &Path, source_stamp: Option<SourceStamp>) -> track::Result<Option<Model>> {
+    let Some(mmap) = binary::map(path).track()? else {
+        return Ok(None);
+    };
+    let Some(mut reader) = Reader::new(&mmap, MAGIC, VERSION, source_stamp).track()? else {
+        return Ok(None);
+    };
+
+    let dependency_count = reader.u32().track()?;
+    // NOTE: Counts come from the file, nothing is preallocated from them before they're read.
+    let mut dependencies = Vec::new();
+    let mut is_stale = false;
+    for _ in 0..dependency_count {
+        let dependency = reader.path().track()?;
+        let stamp = reader.stamp().track()?;
+
+        // NOTE: A dependency that is gone makes the file stale too, loading the source reports it.
+        is_stale |= source_stamp.is_some() && SourceStamp::new(&dependency).ok() != Some(stamp);
+        dependencies.push(dependency);
+    }
+    if is_stale {
+        return Ok(None);
+    }
+
+    if !matches_layout(&mut reader, &VertexDescription::new()).track()? {
+        return Ok(None);
+    }
+
+    let mesh_count = reader.u32().track()?;
+    let material_count = reader.u32().track()?;
+    let texture_count = reader.u32().track()?;
+    let instance_count = reader.u32().track()?;
+
+    let meshes = (0..mesh_count)
+        .map(|_| read_mesh(&mut reader))
+        .collect::<track::Result<Vec<_>>>()?;
+
+    let materials = (0..material_count)
+        .map(|_| {
+            Ok(Material {
+                base_color_factor: Vec4::from(reader.f32s::<4>().track()?),
+                base_color_texture: reader.optional_index().track()?,
+                specular_color: Vec3::from(reader.f32s::<3>().track()?),
+                specular_texture: reader.optional_index().track()?,
+            })
+        })
+        .collect::<track::Result<Vec<_>>>()?;
+
+    let textures = (0..texture_count)
+        .map(|_| {
+            let texture = Texture {
+                width: reader.u32().track()?,
+                height: reader.u32().track()?,
+                pixels: reader.payload().track()?,
+            };
+            texture.check_size().track()?;
+
+            Ok(texture)
+        })
+        .collect::<track::Result<Vec<_>>>()?;
+
+    let instances = (0..instance_count)
+        .map(|_| {
+            Ok(MeshInstance {
+                mesh: reader.u32().track()? as usize,
+                material: reader.optional_index().track()?,
+                transform: Mat4::from_column_slice(&reader.f32s::<16>().track()?),
+            })
+        })
+        .collect::<track::Result<Vec<_>>>()?;
+
+    let model = Model {
+        meshes,
+        materials,
+        textures,
+        instances,
+        dependencies,
+    };
+    validate(&model).track()?;
+
+    Ok(Some(model))
+}
+
+pub fn write(
+    path: &Path,
+    source_stamp: SourceStamp,
+    model: &Model,
+    compression: Compression,
+) -> track::Result<()> {
+    let mut writer = Writer::new(MAGIC, VERSION, source_stamp, compression);
+
+    // NOTE: Dependencies are stored canonicalized, as the same source may be loaded
+    // from another working directory.
+    writer.u32(model.dependencies.len() as u32);
+    for dependency in &model.dependencies {
+        writer.path(&dependency.canonicalize().track()?);
+        writer.stamp(SourceStamp::new(dependency).track()?);
+    }
+
+    let vertex_description = VertexDescription::new();
+    writer.u32(vertex_description.binding.stride);
+    writer.u32(vertex_description.attributes.len() as u32);
+    vertex_description.attributes.iter().for_each(|attribute| {
+        writer.u32(attribute.location);
+        writer.i32(attribute.format.as_raw());
+        writer.u32(attribute.offset);
+    });
+
+    writer.u32(model.meshes.len() as u32);
+    writer.u32(model.materials.len() as u32);
+    writer.u32(model.textures.len() as u32);
+    writer.u32(model.instances.len() as u32);
+
+    model
+        .meshes
+        .iter()
+        .for_each(|mesh| write_mesh(&mut writer, mesh));
+
+    model.materials.iter().for_each(|material| {
+        writer.f32s(material.base_color_factor.as_slice());
+        writer.optional_index(material.base_color_texture);
+        writer.f32s(material.specular_color.as_slice());
+        writer.optional_index(material.specular_texture);
+    });
+
+    model.textures.iter().for_each(|texture| {
+        writer.u32(texture.width);
+        writer.u32(texture.height);
+        writer.payload(&texture.pixels);
+    });
+
+    model.instances.iter().for_each(|instance| {
+        writer.u32(instance.mesh as u32);
+        writer.optional_index(instance.material);
+        writer.f32s(instance.transform.as_slice());
+    });
+
+    binary::write_file(path, &writer.bytes).track()
+}
+
+fn write_mesh(writer: &mut Writer, mesh: &Mesh) {
+    let (min, max) = mesh.bounds();
+    // NOTE: Indices are narrowed to 16 bits whenever they fit and widened back when reading.
+    let index_width = match mesh.vertices.len() <= u16::MAX as usize + 1 {
+        true => 2,
+        false => 4,
+    };
+
+    writer.u32(mesh.vertices.len() as u32);
+    writer.u32(mesh.indices.len() as u32);
+    writer.u32(index_width);
+    writer.f32s(min.as_slice());
+    writer.f32s(max.as_slice());
+
+    // NOTE: Vertices are stored in the memory layout of the host, which is little-endian
+    // on every platform the engine runs on.
+    let mut data = bytes::slice_as_bytes(&mesh.vertices).to_vec();
+    match index_width {
+        2 => mesh
+            .indices
+            .iter()
+            .for_each(|&index| data.extend_from_slice(&(index as u16).to_le_bytes())),
+        _ => mesh
+            .indices
+            .iter()
+            .for_each(|&index| data.extend_from_slice(&index.to_le_bytes())),
+    }
+
+    writer.payload(&data);
+}
+
+fn read_mesh(reader: &mut Reader) -> track::Result<Mesh> {
+    let vertex_count = reader.u32().track()? as usize;
+    let index_count = reader.u32().track()? as usize;
+    let index_width = reader.u32().track()? as usize;
+    if index_width != 2 && index_width != 4 {
+        return Err(InvalidModel(format!("index width of {index_width} bytes"))).track();
+    }
+    // NOTE: Bounds are there for tools and culling, meshes recompute them on demand.
+    reader.f32s::<6>().track()?;
+
+    let data = reader.payload().track()?;
+    let vertices_size = vertex_count * std::mem::size_of::<Vertex>();
+    if data.len() != vertices_size + index_count * index_width {
+        return Err(TruncatedFile).track();
+    }
+
+    let (vertices, indices) = data.split_at(vertices_size);
+    let vertices = unsafe { bytes::copy_from_bytes::<Vertex>(vertices) };
+    let indices = match index_width {
+        2 => indices
+            .chunks_exact(2)
+            .map(|index| u16::from_le_bytes([index[0], index[1]]) as u32)
+            .collect(),
+        _ => indices
+            .chunks_exact(4)
+            .map(|index| u32::from_le_bytes([index[0], index[1], index[2], index[3]]))
+            .collect(),
+    };
+
+    if let Some(index) = indices
+        .iter()
+        .find(|&&index| index as usize >= vertex_count)
+    {
+        return Err(InvalidModel(format!(
+            "index {index} out of {vertex_count} vertices"
+        )))
+        .track();
+    }
+
+    Ok(Mesh { vertices, indices })
+}
+
+/// Checks that every index into the other sections is in range, as a cache file that parses
+/// cleanly may still be left over from a broken write.
+fn validate(model: &Model) -> Result<(), InvalidModel> {
+    let check = |kind: &str, index: Option<usize>, count: usize| match index {
+        Some(index) if index >= count => {
+            Err(InvalidModel(format!("{kind} {index} out of {count}")))
+        }
+        _ => Ok(()),
+    };
+
+    model.materials.iter().try_for_each(|material| {
+        check("texture", material.base_color_texture, model.textures.len())?;
+        check("texture", material.specular_texture, model.textures.len())
+    })?;
+
+    model.instances.iter().try_for_each(|instance| {
+        check("mesh", Some(instance.mesh), model.meshes.len())?;
+        check("material", instance.material, model.materials.len())
+    })
+}
+
+fn matches_layout(
+    reader: &mut Reader,
+    vertex_description: &VertexDescription,
+) -> Result<bool, TruncatedFile> {
+    let stride = reader.u32()?;
+    let attribute_count = reader.u32()? as usize;
+
+    let mut matches = stride == vertex_description.binding.stride
+        && attribute_count == vertex_description.attributes.len();
+    for index in 0..attribute_count {
+        let attribute = (reader.u32()?, reader.i32()?, reader.u32()?);
+
+        matches &= vertex_description
+            .attributes
+            .get(index)
+            .is_some_and(|expected| {
+                attribute == (expected.location, expected.format.as_raw(), expected.offset)
+            });
+    }
+
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use math::Vec2;
+
+    use super::*;
+
+    const STAMP: SourceStamp = SourceStamp {
+        len: 1,
+        modified: 2,
+    };
+
+    fn sample_model() -> Model {
+        let vertex = |x: f32| Vertex {
+            position: Vec3::new(x, 0.0, 0.0),
+            color: Vec3::zeros(),
+            uv: Vec2::zeros(),
+            tangent: Vec4::zeros(),
+        };
+
+        Model {
+            meshes: vec![Mesh {
+                vertices: vec![vertex(0.0), vertex(1.0), vertex(2.0)],
+                indices: vec![0, 1, 2],
+            }],
+            materials: vec![Material {
+                base_color_factor: Vec4::repeat(1.0),
+                base_color_texture: Some(0),
+                specular_color: Vec3::zeros(),
+                specular_texture: None,
+            }],
+            textures: vec![Texture {
+                width: 1,
+                height: 1,
+                pixels: vec![255; 4],
+            }],
+            instances: vec![MeshInstance {
+                mesh: 0,
+                material: Some(0),
+                transform: Mat4::identity(),
+            }],
+            dependencies: Vec::new(),
+        }
+    }
+
+    /// Writes the model into a file of its own and reads it back.
+    fn round_trip(name: &str, model: &Model) -> track::Result<Option<Model>> {
+        let path = std::env::temp_dir().join(format!(
+            "vulkan_learning_{name}_{}.{EXTENSION}",
+            std::process::id()
+        ));
+
+        write(&path, STAMP, model, Compression::default()).track()?;
+        let model = read(&path, Some(STAMP));
+        let _ = std::fs::remove_file(&path);
+
+        model
+    }
+
+    #[test]
+    fn reads_back() {
+        let model = round_trip("reads_back", &sample_model()).unwrap().unwrap();
+
+        assert_eq!(model.meshes[0].vertices.len(), 3);
+        assert_eq!(
+            model.meshes[0].vertices[2].position,
+            Vec3::new(2.0, 0.0, 0.0)
+        );
+        assert_eq!(model.meshes[0].indices, [0, 1, 2]);
+        assert_eq!(model.materials[0].base_color_texture, Some(0));
+        assert_eq!(model.materials[0].specular_texture, None);
+        assert_eq!(model.textures[0].pixels, [255; 4]);
+        assert_eq!(model.instances[0].mesh, 0);
+        assert_eq!(model.instances[0].material, Some(0));
+    }
+
+    #[test]
+    fn rejects_out_of_range_indices() {
+        let mut model = sample_model();
+        model.instances[0].mesh = 1;
+        assert!(round_trip("instance_mesh", &model).is_err());
+
+        let mut model = sample_model();
+        model.instances[0].material = Some(1);
+        assert!(round_trip("instance_material", &model).is_err());
+
+        let mut model = sample_model();
+        model.materials[0].base_color_texture = Some(1);
+        assert!(round_trip("material_texture", &model).is_err());
+
+        let mut model = sample_model();
+        model.meshes[0].indices = vec![0, 1, 3];
+        assert!(round_trip("mesh_index", &model).is_err());
+    }
+
+    #[test]
+    fn rejects_texture_without_its_pixels() {
+        let mut model = sample_model();
+        model.textures[0].pixels.pop();
+
+        assert!(round_trip("texture_pixels", &model).is_err());
+    }
+
+    #[test]
+    fn stale_stamp() {
+        let path = std::env::temp_dir().join(format!(
+            "vulkan_learning_stale_stamp_{}.{EXTENSION}",
+            std::process::id()
+        ));
+        let stale_stamp = SourceStamp {
+            modified: 3,
+            ..STAMP
+        };
+
+        write(&path, STAMP, &sample_model(), Compression::default()).unwrap();
+        let model = read(&path, Some(stale_stamp)).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(model.is_none());
+    }
+}
@@ -0,0 +1,247 @@
+use std::path::{Path, PathBuf};
+
+use math::{Mat4, Vec2, Vec3, Vec4};
+use track::Context;
+
+use crate::engine::asset_system::{
+    mesh::{Mesh, MeshError, NormalGeneration, Vertex},
+    texture::Texture,
+};
+
+use super::{Material, MeshInstance, Model};
+
+/// Loads every triangle primitive of the default scene (or the first one) with the world transforms
+/// of its nodes, together with the materials and both embedded and external textures.
+pub fn load(path: &Path, normal_generation: NormalGeneration) -> track::Result<Model> {
+    let (document, buffers, images) = ::gltf::import(path).track()?;
+
+    let textures = document
+        .textures()
+        .map(|texture| load_texture(&images[texture.source().index()]))
+        .collect::<Result<Vec<_>, _>>()
+        .track()?;
+
+    let materials = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+
+            Material {
+                base_color_factor: Vec4::from(pbr.base_color_factor()),
+                base_color_texture: pbr.base_color_texture().map(|info| info.texture().index()),
+                specular_color: Vec3::zeros(),
+                specular_texture: None,
+            }
+        })
+        .collect();
+
+    // NOTE: Every primitive becomes a separate mesh, `mesh_ranges` maps glTF meshes onto them.
+    let mut meshes = Vec::new();
+    let mut mesh_ranges = Vec::with_capacity(document.meshes().len());
+    for mesh in document.meshes() {
+        let first_mesh = meshes.len();
+
+        for primitive in mesh
+            .primitives()
+            .filter(|primitive| primitive.mode() == ::gltf::mesh::Mode::Triangles)
+        {
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+            let positions: Vec<Vec3> = positions.map(Vec3::from).collect();
+            let normals: Option<Vec<Vec3>> = reader
+                .read_normals()
+                .map(|normals| normals.map(Vec3::from).collect());
+            let uvs: Option<Vec<Vec2>> = reader
+                .read_tex_coords(Default::default())
+                .map(|uvs| uvs.into_f32().map(Vec2::from).collect());
+            let tangents: Option<Vec<Vec4>> = reader
+                .read_tangents()
+                .map(|tangents| tangents.map(Vec4::from).collect());
+
+            check_attribute("normals", normals.as_deref(), positions.len()).track()?;
+            check_attribute("texture coordinates", uvs.as_deref(), positions.len()).track()?;
+            check_attribute("tangents", tangents.as_deref(), positions.len()).track()?;
+
+            let vertices: Vec<Vertex> = positions
+                .iter()
+                .enumerate()
+                .map(|(index, &position)| Vertex {
+                    position,
+                    color: normals
+                        .as_ref()
+                        .map_or_else(Vec3::zeros, |normals| normals[index]),
+                    uv: uvs.as_ref().map_or_else(Vec2::zeros, |uvs| uvs[index]),
+                    tangent: tangents
+                        .as_ref()
+                        .map_or_else(Vec4::zeros, |tangents| tangents[index]),
+                })
+                .collect();
+
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..vertices.len() as u32).collect(),
+            };
+
+            // NOTE: Generated normals invalidate the tangents of the file, so they are regenerated too.
+            let mesh = Mesh::with_generated_attributes(
+                vertices,
+                indices,
+                normals.is_none().then_some(normal_generation),
+                uvs.is_some() && (tangents.is_none() || normals.is_none()),
+            )
+            .track()?;
+
+            meshes.push((mesh, primitive.material().index()));
+        }
+
+        mesh_ranges.push(first_mesh..meshes.len());
+    }
+
+    let mut instances = Vec::new();
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next());
+    if let Some(scene) = scene {
+        scene.nodes().for_each(|node| {
+            collect_instances(
+                &node,
+                Mat4::identity(),
+                &meshes,
+                &mesh_ranges,
+                &mut instances,
+            )
+        });
+    }
+
+    Ok(Model {
+        meshes: meshes.into_iter().map(|(mesh, _)| mesh).collect(),
+        materials,
+        textures,
+        instances,
+        dependencies: collect_dependencies(&document, path),
+    })
+}
+
+/// External buffers and images the document references, embedded and `data:` ones aren't files.
+fn collect_dependencies(document: &::gltf::Document, path: &Path) -> Vec<PathBuf> {
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let buffer_uris = document
+        .buffers()
+        .filter_map(|buffer| match buffer.source() {
+            ::gltf::buffer::Source::Uri(uri) => Some(uri),
+            ::gltf::buffer::Source::Bin => None,
+        });
+    let image_uris = document.images().filter_map(|image| match image.source() {
+        ::gltf::image::Source::Uri { uri, .. } => Some(uri),
+        ::gltf::image::Source::View { .. } => None,
+    });
+
+    buffer_uris
+        .chain(image_uris)
+        .filter(|uri| !uri.contains(':'))
+        .map(|uri| directory.join(uri))
+        .collect()
+}
+
+#[inline(always)]
+fn check_attribute<T>(
+    attribute: &'static str,
+    values: Option<&[T]>,
+    vertex_count: usize,
+) -> Result<(), MeshError> {
+    match values {
+        Some(values) if values.len() != vertex_count => Err(MeshError::MismatchedAttribute {
+            attribute,
+            count: values.len(),
+            vertex_count,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn collect_instances(
+    node: &::gltf::Node,
+    parent_transform: Mat4,
+    meshes: &[(Mesh, Option<usize>)],
+    mesh_ranges: &[std::ops::Range<usize>],
+    instances: &mut Vec<MeshInstance>,
+) {
+    let transform = parent_transform * Mat4::from(node.transform().matrix());
+
+    if let Some(mesh) = node.mesh() {
+        instances.extend(mesh_ranges[mesh.index()].clone().map(|mesh| MeshInstance {
+            mesh,
+            material: meshes[mesh].1,
+            transform,
+        }));
+    }
+
+    node.children()
+        .for_each(|child| collect_instances(&child, transform, meshes, mesh_ranges, instances));
+}
+
+/// Converts decoded glTF image data into RGBA8, 16-bit channels are truncated to 8 bits.
+fn load_texture(image: &::gltf::image::Data) -> Result<Texture, UnsupportedImageFormat> {
+    use ::gltf::image::Format;
+
+    let pixels = match image.format {
+        Format::R8G8B8A8 => image.pixels.clone(),
+        Format::R8G8B8 => image
+            .pixels
+            .chunks_exact(3)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], u8::MAX])
+            .collect(),
+        Format::R8G8 => image
+            .pixels
+            .chunks_exact(2)
+            .flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+            .collect(),
+        Format::R8 => image
+            .pixels
+            .iter()
+            .flat_map(|&pixel| [pixel, pixel, pixel, u8::MAX])
+            .collect(),
+        Format::R16G16B16A16 => image
+            .pixels
+            .chunks_exact(8)
+            .flat_map(|pixel| [pixel[1], pixel[3], pixel[5], pixel[7]])
+            .collect(),
+        Format::R16G16B16 => image
+            .pixels
+            .chunks_exact(6)
+            .flat_map(|pixel| [pixel[1], pixel[3], pixel[5], u8::MAX])
+            .collect(),
+        Format::R16G16 => image
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[1], pixel[1], pixel[1], pixel[3]])
+            .collect(),
+        Format::R16 => image
+            .pixels
+            .chunks_exact(2)
+            .flat_map(|pixel| [pixel[1], pixel[1], pixel[1], u8::MAX])
+            .collect(),
+        format => return Err(UnsupportedImageFormat(format)),
+    };
+
+    Ok(Texture {
+        width: image.width,
+        height: image.height,
+        pixels,
+    })
+}
+
+/// glTF image in a format without an RGBA8 conversion, e.g. floating-point ones.
+#[derive(Debug)]
+pub struct UnsupportedImageFormat(::gltf::image::Format);
+
+impl std::fmt::Display for UnsupportedImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unsupported glTF image format: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnsupportedImageFormat {}
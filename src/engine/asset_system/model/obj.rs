@@ -0,0 +1,121 @@
+use std::{cell::RefCell, collections::HashMap, fs::File, io::BufReader, path::Path};
+
+use math::{Mat4, Vec3, Vec4};
+use tracing::warn;
+use track::Context;
+
+use crate::engine::asset_system::{
+    mesh::{Mesh, MeshError, NormalGeneration},
+    texture::Texture,
+};
+
+use super::{Material, MeshInstance, Model};
+
+/// Loads every object with triangles as its own mesh together with its MTL material.
+///
+/// Textures are resolved relative to the file and loaded once even if shared by several materials.
+pub fn load(path: &Path, normal_generation: NormalGeneration) -> track::Result<Model> {
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+    // NOTE: Material libraries are loaded like `tobj::load_obj` does, only remembering them.
+    let material_libraries = RefCell::new(Vec::new());
+    let (models, materials) = tobj::load_obj_buf(
+        &mut BufReader::new(File::open(path).track()?),
+        &tobj::LoadOptions {
+            triangulate: true,
+            ignore_points: true,
+            ignore_lines: true,
+            single_index: true,
+        },
+        |material_path| {
+            let material_path = directory.join(material_path);
+            let materials = tobj::load_mtl(&material_path);
+            if materials.is_ok() {
+                material_libraries.borrow_mut().push(material_path);
+            }
+
+            materials
+        },
+    )
+    .track()?;
+    let mut dependencies = material_libraries.into_inner();
+
+    let materials = materials.unwrap_or_else(|error| {
+        warn!("Failed to load the materials of {path:?}: {error}");
+
+        Default::default()
+    });
+
+    let mut textures = Vec::new();
+    let mut texture_indices = HashMap::new();
+    let mut load_texture = |texture_path: &str| -> track::Result<Option<usize>> {
+        if texture_path.is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(&index) = texture_indices.get(texture_path) {
+            return Ok(Some(index));
+        }
+
+        let full_path = directory.join(texture_path);
+        textures.push(Texture::new(&full_path).track()?);
+        dependencies.push(full_path);
+        texture_indices.insert(texture_path.to_owned(), textures.len() - 1);
+
+        Ok(Some(textures.len() - 1))
+    };
+
+    let materials = materials
+        .into_iter()
+        .map(|material| {
+            Ok(Material {
+                base_color_factor: Vec4::new(
+                    material.diffuse[0],
+                    material.diffuse[1],
+                    material.diffuse[2],
+                    material.dissolve,
+                ),
+                base_color_texture: load_texture(&material.diffuse_texture).track()?,
+                specular_color: Vec3::from(material.specular),
+                specular_texture: load_texture(&material.specular_texture).track()?,
+            })
+        })
+        .collect::<track::Result<Vec<_>>>()?;
+
+    // NOTE: Objects of only points or lines, or empty groups, are common helpers, they are skipped
+    // rather than failing the model.
+    let (meshes, instances): (Vec<_>, Vec<_>) = models
+        .into_iter()
+        .filter(|model| !model.mesh.indices.is_empty())
+        .enumerate()
+        .map(|(index, model)| {
+            let instance = MeshInstance {
+                mesh: index,
+                material: model
+                    .mesh
+                    .material_id
+                    .filter(|&material| material < materials.len()),
+                transform: Mat4::identity(),
+            };
+
+            Ok((
+                Mesh::from_obj(model.mesh, normal_generation).track()?,
+                instance,
+            ))
+        })
+        .collect::<track::Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    if meshes.is_empty() {
+        return Err(MeshError::Empty).track();
+    }
+
+    Ok(Model {
+        meshes,
+        materials,
+        textures,
+        instances,
+        dependencies,
+    })
+}
@@ -0,0 +1,258 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    mem,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Weak,
+    },
+};
+
+use smallvec::SmallVec;
+use tracing::error;
+use track::Context;
+
+use super::{
+    handle::{AssetId, DropGuard, Handle, RawId},
+    Asset, AssetState,
+};
+use crate::engine::renderer::Renderer;
+
+type Parsed<T> = (RawId, track::Result<<T as Asset>::Source>);
+
+/// What an asset was loaded from, each asset may be known under several keys.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AssetKey {
+    Path(PathBuf),
+    ContentHash(u64),
+}
+
+struct Entry<T: Asset> {
+    state: AssetState<T>,
+    guard: Weak<DropGuard>,
+    keys: SmallVec<[AssetKey; 2]>,
+}
+
+struct Slot<T: Asset> {
+    generation: u32,
+    entry: Option<Entry<T>>,
+}
+
+/// Storage of a single asset type, deduplicated by [`AssetKey`] and reference counted by [`Handle`].
+///
+/// Assets parsed on worker threads come back through a channel and wait in a queue for their upload.
+pub struct Registry<T: Asset> {
+    slots: Vec<Slot<T>>,
+    free_slots: Vec<u32>,
+    keys: HashMap<AssetKey, RawId>,
+    drop_sender: Sender<RawId>,
+    drop_receiver: Receiver<RawId>,
+    parsed_sender: Sender<Parsed<T>>,
+    parsed_receiver: Receiver<Parsed<T>>,
+    upload_queue: VecDeque<RawId>,
+}
+
+impl<T: Asset> Default for Registry<T> {
+    fn default() -> Self {
+        let (drop_sender, drop_receiver) = mpsc::channel();
+        let (parsed_sender, parsed_receiver) = mpsc::channel();
+
+        Self {
+            slots: Default::default(),
+            free_slots: Default::default(),
+            keys: Default::default(),
+            drop_sender,
+            drop_receiver,
+            parsed_sender,
+            parsed_receiver,
+            upload_queue: Default::default(),
+        }
+    }
+}
+
+impl<T: Asset> Registry<T> {
+    /// Returns a new handle to the asset known under `key`, if it's still alive.
+    pub fn find(&mut self, key: &AssetKey) -> Option<Handle<T>> {
+        let raw = *self.keys.get(key)?;
+        let guard = self.entry(raw)?.guard.upgrade()?;
+
+        Some(Handle::new(AssetId::new(raw), guard))
+    }
+
+    /// Makes the asset of `handle` known under `key` as well.
+    #[inline]
+    pub fn alias(&mut self, key: AssetKey, handle: &Handle<T>) {
+        let raw = handle.id().raw();
+
+        if let Some(entry) = Self::slot_entry_mut(&mut self.slots, raw) {
+            entry.keys.push(key.clone());
+            self.keys.insert(key, raw);
+        }
+    }
+
+    pub fn insert(&mut self, keys: SmallVec<[AssetKey; 2]>, state: AssetState<T>) -> Handle<T> {
+        let index = match self.free_slots.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    entry: None,
+                });
+
+                (self.slots.len() - 1) as u32
+            }
+        };
+
+        let raw = RawId {
+            index,
+            generation: self.slots[index as usize].generation,
+        };
+        let guard = Arc::new(DropGuard::new(raw, self.drop_sender.clone()));
+
+        // NOTE: A key may still point to an asset whose handles are gone but whose drop
+        // wasn't collected yet, the new asset takes it over.
+        keys.iter().for_each(|key| {
+            self.keys.insert(key.clone(), raw);
+        });
+
+        self.slots[index as usize].entry = Some(Entry {
+            state,
+            guard: Arc::downgrade(&guard),
+            keys,
+        });
+
+        Handle::new(AssetId::new(raw), guard)
+    }
+
+    #[inline(always)]
+    pub fn get(&self, handle: &Handle<T>) -> &AssetState<T> {
+        self.get_by_id(handle.id())
+            .unwrap_or_else(|| panic!("Handle of another registry: {handle:?}"))
+    }
+
+    #[inline(always)]
+    pub fn get_by_id(&self, id: AssetId<T>) -> Option<&AssetState<T>> {
+        self.entry(id.raw()).map(|entry| &entry.state)
+    }
+
+    /// Sender for the result of parsing the asset of `handle`, meant to be moved into a worker thread.
+    ///
+    /// The asset must be inserted as [`AssetState::Loading`].
+    #[inline(always)]
+    pub fn parsed_sender(&self) -> Sender<Parsed<T>> {
+        self.parsed_sender.clone()
+    }
+
+    /// Queues the assets parsed since the last call for their upload, failed ones are logged.
+    pub fn receive_parsed(&mut self) {
+        while let Ok((raw, source)) = self.parsed_receiver.try_recv() {
+            // NOTE: Assets dropped while being parsed are gone already.
+            let Some(entry) = Self::slot_entry_mut(&mut self.slots, raw) else {
+                continue;
+            };
+
+            match source {
+                Ok(source) => {
+                    entry.state = AssetState::Queued(source);
+                    self.upload_queue.push_back(raw);
+                }
+                Err(error) => {
+                    error!("Failed to load {:?}: {error}", entry.keys.first());
+                    entry.state = AssetState::Failed;
+                }
+            }
+        }
+    }
+
+    /// Starts streaming the next queued asset, returns `None` if there is nothing to upload.
+    pub fn begin_next_upload(&mut self, renderer: &mut Renderer) -> track::Result<Option<RawId>> {
+        while let Some(raw) = self.upload_queue.pop_front() {
+            let Some(entry) = self.entry_mut(raw) else {
+                continue;
+            };
+
+            // NOTE: Stays failed if the upload can't begin.
+            match mem::replace(&mut entry.state, AssetState::Failed) {
+                AssetState::Queued(source) => {
+                    entry.state = AssetState::Uploading(T::begin_upload(source, renderer).track()?);
+
+                    return Ok(Some(raw));
+                }
+                state => entry.state = state,
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Marks the asset whose streamed upload completed as ready for drawing.
+    pub fn finish_upload(&mut self, raw: RawId) {
+        if let Some(entry) = self.entry_mut(raw) {
+            entry.state = match mem::replace(&mut entry.state, AssetState::Failed) {
+                AssetState::Uploading(asset) => AssetState::Resident(asset),
+                state => state,
+            };
+        }
+    }
+
+    /// Removes every asset whose last handle was dropped and returns them, so their GPU data can be freed.
+    pub fn collect_garbage(&mut self) -> Vec<AssetState<T>> {
+        let mut assets = Vec::new();
+
+        while let Ok(raw) = self.drop_receiver.try_recv() {
+            // NOTE: Drops of assets that were already replaced are stale.
+            let Some(slot) = self
+                .slots
+                .get_mut(raw.index as usize)
+                .filter(|slot| slot.generation == raw.generation)
+            else {
+                continue;
+            };
+
+            let Some(entry) = slot.entry.take() else {
+                continue;
+            };
+
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free_slots.push(raw.index);
+
+            entry.keys.iter().for_each(|key| {
+                if self.keys.get(key) == Some(&raw) {
+                    self.keys.remove(key);
+                }
+            });
+
+            assets.push(entry.state);
+        }
+
+        assets
+    }
+
+    /// Count of alive assets.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free_slots.len()
+    }
+
+    #[inline(always)]
+    fn entry(&self, raw: RawId) -> Option<&Entry<T>> {
+        self.slots
+            .get(raw.index as usize)
+            .filter(|slot| slot.generation == raw.generation)
+            .and_then(|slot| slot.entry.as_ref())
+    }
+
+    #[inline(always)]
+    fn entry_mut(&mut self, raw: RawId) -> Option<&mut Entry<T>> {
+        Self::slot_entry_mut(&mut self.slots, raw)
+    }
+
+    /// Same as [`Registry::entry_mut`], borrowing only the slots.
+    #[inline(always)]
+    fn slot_entry_mut(slots: &mut [Slot<T>], raw: RawId) -> Option<&mut Entry<T>> {
+        slots
+            .get_mut(raw.index as usize)
+            .filter(|slot| slot.generation == raw.generation)
+            .and_then(|slot| slot.entry.as_mut())
+    }
+}
@@ -0,0 +1,66 @@
+use std::{fmt, path::Path};
+
+use track::Context;
+
+pub mod cache;
+
+/// Decoded image with tightly packed RGBA8 pixels, ready to be uploaded into a sampled image.
+pub struct Texture {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Texture {
+    /// Decodes a PNG, JPEG or TGA file, the format is guessed from the content.
+    pub fn new<P: AsRef<Path> + std::fmt::Debug>(path: P) -> track::Result<Self> {
+        let image = image::io::Reader::open(path)
+            .track()?
+            .with_guessed_format()
+            .track()?
+            .decode()
+            .track()?
+            .into_rgba8();
+
+        Ok(Self {
+            width: image.width(),
+            height: image.height(),
+            pixels: image.into_raw(),
+        })
+    }
+
+    /// Checks that the extent isn't empty and the pixels fill it exactly,
+    /// as uploads copy the whole extent.
+    pub fn check_size(&self) -> Result<(), InvalidTextureSize> {
+        let expected_len = self.width as u64 * self.height as u64 * 4;
+
+        match self.width > 0 && self.height > 0 && self.pixels.len() as u64 == expected_len {
+            true => Ok(()),
+            false => Err(InvalidTextureSize {
+                width: self.width,
+                height: self.height,
+                pixels_len: self.pixels.len(),
+            }),
+        }
+    }
+}
+
+/// Texture whose extent is empty or doesn't match its pixels.
+#[derive(Debug)]
+pub struct InvalidTextureSize {
+    pub width: u32,
+    pub height: u32,
+    pub pixels_len: usize,
+}
+
+impl fmt::Display for InvalidTextureSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Texture of {}x{} has {} bytes of RGBA8 pixels",
+            self.width, self.height, self.pixels_len
+        )
+    }
+}
+
+impl std::error::Error for InvalidTextureSize {}
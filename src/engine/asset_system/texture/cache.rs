@@ -0,0 +1,54 @@
+//! Binary texture format written by the cooker: after the common header of
+//! [`binary`](crate::engine::asset_system::binary) come the width, the height
+//! and a payload of RGBA8 pixels.
+
+use std::path::Path;
+
+use track::Context;
+
+use crate::engine::asset_system::binary::{self, Reader, Writer};
+pub use crate::engine::asset_system::binary::{Compression, SourceStamp};
+
+use super::Texture;
+
+pub const EXTENSION: &str = "vltex";
+
+const MAGIC: [u8; 4] = *b"VLTX";
+const VERSION: u32 = 1;
+
+/// Reads the binary texture, returns `None` if there is none or it's stale.
+///
+/// Without `source_stamp` the file is trusted to match its source, e.g. for cooked assets.
+pub fn read(path: &Path, source_stamp: Option<SourceStamp>) -> track::Result<Option<Texture>> {
+    let Some(mmap) = binary::map(path).track()? else {
+        return Ok(None);
+    };
+    let Some(mut reader) = Reader::new(&mmap, MAGIC, VERSION, source_stamp).track()? else {
+        return Ok(None);
+    };
+
+    let texture = Texture {
+        width: reader.u32().track()?,
+        height: reader.u32().track()?,
+        pixels: reader.payload().track()?,
+    };
+    // NOTE: Shipping builds have no source to fall back to, a broken file must not reach the GPU.
+    texture.check_size().track()?;
+
+    Ok(Some(texture))
+}
+
+pub fn write(
+    path: &Path,
+    source_stamp: SourceStamp,
+    texture: &Texture,
+    compression: Compression,
+) -> track::Result<()> {
+    let mut writer = Writer::new(MAGIC, VERSION, source_stamp, compression);
+
+    writer.u32(texture.width);
+    writer.u32(texture.height);
+    writer.payload(&texture.pixels);
+
+    binary::write_file(path, &writer.bytes).track()
+}
@@ -0,0 +1,98 @@
+use math::{Mat4, Vec3};
+
+pub enum Projection {
+    Perspective {
+        /// Vertical field of view in radians.
+        fov_y: f32,
+        near: f32,
+        far: f32,
+    },
+    Orthographic {
+        /// Height of the visible area in world units, the width follows the aspect ratio.
+        height: f32,
+        near: f32,
+        far: f32,
+    },
+}
+
+pub struct Camera {
+    pub position: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    pub projection: Projection,
+}
+
+impl Camera {
+    pub const DEFAULT_FOV_Y: f32 = std::f32::consts::FRAC_PI_3;
+    pub const DEFAULT_NEAR: f32 = 0.1;
+    pub const DEFAULT_FAR: f32 = 200.0;
+
+    #[inline]
+    pub fn perspective(position: Vec3, target: Vec3, fov_y: f32, near: f32, far: f32) -> Self {
+        Self {
+            position,
+            target,
+            up: Vec3::y(),
+            projection: Projection::Perspective { fov_y, near, far },
+        }
+    }
+
+    #[inline]
+    pub fn orthographic(position: Vec3, target: Vec3, height: f32, near: f32, far: f32) -> Self {
+        Self {
+            position,
+            target,
+            up: Vec3::y(),
+            projection: Projection::Orthographic { height, near, far },
+        }
+    }
+
+    #[inline(always)]
+    pub fn view(&self) -> Mat4 {
+        math::look_at_rh(&self.position, &self.target, &self.up)
+    }
+
+    /// Projection into Vulkan clip space: depth in `0..1` and Y pointing down.
+    #[inline]
+    pub fn projection(&self, aspect_ratio: f32) -> Mat4 {
+        let mut projection = match self.projection {
+            Projection::Perspective { fov_y, near, far } => {
+                math::perspective_rh_zo(aspect_ratio, fov_y, near, far)
+            }
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * aspect_ratio;
+
+                math::ortho_rh_zo(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    near,
+                    far,
+                )
+            }
+        };
+        projection[(1, 1)] *= -1.0;
+
+        projection
+    }
+
+    #[inline(always)]
+    pub fn view_projection(&self, aspect_ratio: f32) -> Mat4 {
+        self.projection(aspect_ratio) * self.view()
+    }
+}
+
+impl Default for Camera {
+    #[inline]
+    fn default() -> Self {
+        Self::perspective(
+            Vec3::new(2.0, 2.0, 4.0),
+            Vec3::zeros(),
+            Self::DEFAULT_FOV_Y,
+            Self::DEFAULT_NEAR,
+            Self::DEFAULT_FAR,
+        )
+    }
+}
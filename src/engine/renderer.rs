@@ -1,73 +1,336 @@
-use std::{mem::ManuallyDrop, path::Path};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    mem::ManuallyDrop,
+    path::Path,
+};
 
-use ash::{prelude::VkResult, vk};
-use tracing::info;
+use ash::vk;
+use smallvec::SmallVec;
+use tracing::{info, warn};
 use track::Context;
 
-use crate::profile;
+use crate::{
+    engine::utils::{bytes, paths},
+    profile,
+};
 
-use super::asset_system::mesh;
+use super::{
+    asset_system::{mesh, texture},
+    camera::Camera,
+};
 
 mod context;
 mod resources;
 
+pub use context::{
+    BlendMode, ComputePipelineId, PipelineBuilder, PipelineId, DEFAULT_FRAMES_IN_FLIGHT,
+};
+pub use resources::{MeshHandle, StorageBufferHandle, StorageImageHandle, TextureHandle};
+
+/// Mesh placed into the world by its model matrix.
+///
+/// Objects with a texture are drawn by the textured mesh pipeline.
+pub struct RenderObject {
+    pub mesh_handle: MeshHandle,
+    pub texture_handle: Option<TextureHandle>,
+    /// Overrides the built-in pipeline picked by whether the object has a texture.
+    pub pipeline: Option<PipelineId>,
+    pub transform: math::Mat4,
+}
+
+/// Storage resource bound to a descriptor of a compute shader.
+#[derive(Clone, Copy, Debug)]
+pub enum ComputeBinding {
+    StorageBuffer(StorageBufferHandle),
+    StorageImage(StorageImageHandle),
+}
+
+/// Workgroups a dispatch runs.
+#[derive(Clone, Copy, Debug)]
+pub enum DispatchSize {
+    Groups([u32; 3]),
+    /// Enough workgroups to cover the invocations along each axis, e.g. the pixels of an image.
+    Invocations([u32; 3]),
+    /// Group counts read by the GPU from a `VkDispatchIndirectCommand` at `offset` in the buffer,
+    /// e.g. written by a previous dispatch.
+    Indirect {
+        buffer: StorageBufferHandle,
+        offset: u64,
+    },
+}
+
+/// Compute work recorded ahead of the graphics work of the next drawn frame.
+pub struct ComputeDispatch {
+    pub pipeline: ComputePipelineId,
+    /// Resources by their binding in descriptor set 0 of the shader, one for every binding it declares.
+    pub bindings: SmallVec<[(u32, ComputeBinding); 4]>,
+    /// Bytes of the shader's push constant block from its start, empty if it has none.
+    pub push_constants: Vec<u8>,
+    pub size: DispatchSize,
+}
+
+impl ComputeDispatch {
+    /// Checks the bindings and push constants against what the shader of the pipeline declares.
+    fn check(
+        &self,
+        pipeline_handle: &context::ComputePipelineHandle,
+    ) -> Result<(), InvalidDispatch> {
+        for (index, &(binding, compute_binding)) in self.bindings.iter().enumerate() {
+            let descriptor_type = match compute_binding {
+                ComputeBinding::StorageBuffer(_) => vk::DescriptorType::STORAGE_BUFFER,
+                ComputeBinding::StorageImage(_) => vk::DescriptorType::STORAGE_IMAGE,
+            };
+
+            let declared = pipeline_handle
+                .bindings
+                .iter()
+                .find(|declared| (declared.set, declared.binding) == (0, binding));
+            match declared {
+                Some(declared) if declared.descriptor_type != descriptor_type => {
+                    return Err(InvalidDispatch(format!(
+                        "binding {binding} is a {:?}, not a {descriptor_type:?}",
+                        declared.descriptor_type
+                    )));
+                }
+                Some(_) => (),
+                None => {
+                    return Err(InvalidDispatch(format!(
+                        "binding {binding} isn't declared by the shader"
+                    )));
+                }
+            }
+
+            if self.bindings[..index]
+                .iter()
+                .any(|&(other_binding, _)| other_binding == binding)
+            {
+                return Err(InvalidDispatch(format!("binding {binding} is bound twice")));
+            }
+        }
+
+        // NOTE: Only set 0 is bound, and a descriptor left unwritten must not be read by the shader.
+        let missing = pipeline_handle.bindings.iter().find(|declared| {
+            declared.set != 0
+                || !self
+                    .bindings
+                    .iter()
+                    .any(|&(binding, _)| binding == declared.binding)
+        });
+        if let Some(missing) = missing {
+            return Err(InvalidDispatch(format!(
+                "set {}, binding {} declared by the shader isn't bound",
+                missing.set, missing.binding
+            )));
+        }
+
+        let push_constants_len = pipeline_handle
+            .push_constant_range
+            .map_or(0, |range| range.offset + range.size) as usize;
+        if self.push_constants.len() != push_constants_len {
+            return Err(InvalidDispatch(format!(
+                "{} bytes of push constants for a block of {push_constants_len}",
+                self.push_constants.len()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// GPU resource released by its owner, destroyed once no frame in flight can use it.
+enum RetiredResource {
+    Mesh(MeshHandle),
+    Texture(TextureHandle),
+    StorageBuffer(StorageBufferHandle),
+    StorageImage(StorageImageHandle),
+}
+
 pub struct Renderer {
     context: context::Context,
     resources: ManuallyDrop<resources::Resources>,
-    render_fence: vk::Fence,
-    render_semaphore: vk::Semaphore,
-    present_semaphore: vk::Semaphore,
+    /// Resources with the count of frame fences left to wait before destroying them.
+    retired_resources: Vec<(usize, RetiredResource)>,
+    /// Staging buffers of the upload in the upload stream.
+    streamed_upload: Option<resources::StagingBatch>,
+    /// Dispatches queued since the last drawn frame.
+    pending_dispatches: Vec<ComputeDispatch>,
+    frame_index: usize,
+    window_extent: vk::Extent2D,
+    is_swapchain_outdated: bool,
 }
 
 impl Renderer {
-    pub unsafe fn new(window: &winit::window::Window) -> track::Result<Self> {
+    /// Creates a renderer drawing into the window, recording up to `frames_in_flight` frames
+    /// ahead of the GPU.
+    #[inline(always)]
+    pub unsafe fn new(
+        window: &winit::window::Window,
+        frames_in_flight: usize,
+    ) -> track::Result<Self> {
         info!("Initializing Vulkan");
-        let (context, resources) = context::Context::new(window).track()?;
+        let (context, resources) = context::Context::new(window, frames_in_flight).track()?;
+
+        Self::from_context(context, resources)
+    }
 
-        let fence_info = vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED);
-        let render_fence = context.create_fence(&fence_info).track()?;
+    /// Creates a renderer that draws into an offscreen image instead of a window.
+    ///
+    /// Frames are read back with [`Renderer::capture`].
+    #[inline(always)]
+    pub unsafe fn new_headless(
+        image_extent: vk::Extent2D,
+        frames_in_flight: usize,
+    ) -> track::Result<Self> {
+        if image_extent.width == 0 || image_extent.height == 0 {
+            return Err(EmptyHeadlessExtent(image_extent)).track();
+        }
 
-        let semaphore_info = vk::SemaphoreCreateInfo::default();
-        let render_semaphore = context.create_semaphore(&semaphore_info).track()?;
-        let present_semaphore = context.create_semaphore(&semaphore_info).track()?;
+        info!("Initializing headless Vulkan");
+        let (context, resources) =
+            context::Context::new_headless(image_extent, frames_in_flight).track()?;
+
+        Self::from_context(context, resources)
+    }
+
+    unsafe fn from_context(
+        context: context::Context,
+        resources: resources::Resources,
+    ) -> track::Result<Self> {
+        let window_extent = context.render_target.image_extent();
 
         info!("Rensderer prepared");
 
         Ok(Self {
             context,
             resources: ManuallyDrop::new(resources),
-            render_fence,
-            render_semaphore,
-            present_semaphore,
+            retired_resources: Default::default(),
+            streamed_upload: None,
+            pending_dispatches: Default::default(),
+            frame_index: Default::default(),
+            window_extent,
+            is_swapchain_outdated: false,
         })
     }
 
+    /// Schedules the swapchain recreation for the next frame.
+    ///
+    /// A zero-sized extent (minimized window) pauses drawing until the window is restored.
     #[inline(always)]
-    pub unsafe fn draw(
-        &self,
-        mesh: &mesh::Mesh,
-        // meshes: &Vec<[mesh::Mesh; super::Engine::DEFAULT_STACK_BASED_MESHES_SIZE]>,
-    ) -> VkResult<()> {
+    pub fn resize(&mut self, window_extent: vk::Extent2D) {
+        self.window_extent = window_extent;
+        self.is_swapchain_outdated = true;
+    }
+
+    #[inline(always)]
+    pub unsafe fn draw(&mut self, camera: &Camera, objects: &[RenderObject]) -> track::Result<()> {
         profile!("Draw Triangle");
 
+        if self.window_extent.width == 0 || self.window_extent.height == 0 {
+            return Ok(());
+        }
+
+        if self.is_swapchain_outdated {
+            self.context
+                .recreate_swapchain(&mut self.resources, self.window_extent)
+                .track()?;
+            self.is_swapchain_outdated = false;
+        }
+
+        #[cfg(feature = "hot_reload")]
+        self.context.reload_changed_shaders().track()?;
+
+        self.context
+            .wait_for_fences(&[self.context.frames[self.frame_index].render_fence])
+            .track()?;
+        self.destroy_retired_resources();
+
         let device = &self.context.device_handle.device;
-        let queue_family_index = self.context.device_handle.queue_family_index;
+        let queue_family_index = self.context.device_handle.queues.graphics.family_index;
+
+        let scene_set_layout = self.context.scene_set_layout();
+        let texture_set_layout = self.context.texture_set_layout();
+        let (scene_set, texture_sets, compute_sets) = {
+            let descriptor_allocator =
+                &mut self.context.frames[self.frame_index].descriptor_allocator;
+
+            descriptor_allocator.reset(device).track()?;
+            let scene_set = descriptor_allocator
+                .allocate(device, scene_set_layout)
+                .track()?;
 
-        self.context.reset_fences(&[self.render_fence])?;
-        self.context.reset_commmand_buffers()?;
+            let mut texture_sets = HashMap::new();
+            for texture_handle in objects.iter().filter_map(|object| object.texture_handle) {
+                if let Entry::Vacant(entry) = texture_sets.entry(texture_handle) {
+                    entry.insert(
+                        descriptor_allocator
+                            .allocate(device, texture_set_layout)
+                            .track()?,
+                    );
+                }
+            }
+
+            let compute_sets = self
+                .pending_dispatches
+                .iter()
+                .map(|dispatch| {
+                    let pipeline_handle = self.context.pipelines.get_compute(dispatch.pipeline);
 
-        let (image_index, image, image_view) = self
+                    pipeline_handle
+                        .set_layouts
+                        .first()
+                        .map(|&set_layout| descriptor_allocator.allocate(device, set_layout))
+                        .transpose()
+                })
+                .collect::<track::Result<SmallVec<[_; 4]>>>()?;
+
+            (scene_set, texture_sets, compute_sets)
+        };
+
+        let frame = &self.context.frames[self.frame_index];
+
+        let (image_index, image, image_view, is_suboptimal) = match self
             .context
-            .get_image(self.present_semaphore, vk::Fence::null())?;
+            .get_image(frame.present_semaphore, vk::Fence::null())
+        {
+            Ok(image) => image,
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                self.is_swapchain_outdated = true;
+
+                return Ok(());
+            }
+            Err(error) => return Err(error).track(),
+        };
 
-        let command_buffer = self.context.command.command_buffers[0];
+        // NOTE: A suboptimal image is still drawn and presented, the swapchain is recreated
+        // at the start of the next frame.
+        self.is_swapchain_outdated |= is_suboptimal;
+
+        self.context.reset_fences(&[frame.render_fence]).track()?;
+        self.context
+            .reset_commmand_buffers(frame.command.command_pool)
+            .track()?;
+
+        let command_buffer = frame.command_buffer();
         let command_buffer_begin_info = vk::CommandBufferBeginInfo::default()
             .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
 
-        device.begin_command_buffer(command_buffer, &command_buffer_begin_info)?;
+        device
+            .begin_command_buffer(command_buffer, &command_buffer_begin_info)
+            .track()?;
+
+        if !self.pending_dispatches.is_empty() {
+            self.record_dispatches(command_buffer, &compute_sets);
+            self.pending_dispatches.clear();
+        }
 
+        // NOTE: Source stages make the barriers wait for the previous frames in flight,
+        // they share the depth buffer and, when rendering offscreen, the color image.
         let memory_barriers = [
             vk::ImageMemoryBarrier2::default()
+                .src_stage_mask(
+                    vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT
+                        | vk::PipelineStageFlags2::COPY,
+                )
                 .dst_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
                 .dst_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
                 .new_layout(vk::ImageLayout::ATTACHMENT_OPTIMAL)
@@ -80,28 +343,12 @@ impl Renderer {
                     layer_count: 1,
                     ..Default::default()
                 }),
-            vk::ImageMemoryBarrier2::default()
-                .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
-                .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
-                .dst_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
-                .dst_access_mask(vk::AccessFlags2::NONE)
-                .old_layout(vk::ImageLayout::ATTACHMENT_OPTIMAL)
-                .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
-                .src_queue_family_index(queue_family_index)
-                .dst_queue_family_index(queue_family_index)
-                .image(image)
-                .subresource_range(vk::ImageSubresourceRange {
-                    aspect_mask: vk::ImageAspectFlags::COLOR,
-                    base_mip_level: 0,
-                    level_count: 1,
-                    base_array_layer: 0,
-                    layer_count: 1,
-                }),
             vk::ImageMemoryBarrier2::default()
                 .src_stage_mask(
                     vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS
                         | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
                 )
+                .src_access_mask(vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE)
                 .dst_stage_mask(
                     vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS
                         | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
@@ -150,64 +397,888 @@ impl Renderer {
             .color_attachments(&color_attachment_infos)
             .depth_attachment(&depth_attachment_info)
             .render_area(vk::Rect2D {
-                extent: self.context.swapchain_handle.image_extent,
+                extent: self.context.render_target.image_extent(),
                 offset: Default::default(),
             })
             .layer_count(1);
 
         device.cmd_begin_rendering(command_buffer, &rendering_info);
 
-        device.cmd_bind_pipeline(
-            command_buffer,
-            vk::PipelineBindPoint::GRAPHICS,
-            self.context.pipeline_handle.pipeline,
-        );
+        // NOTE: Every pipeline takes its viewport and scissor from here, none is baked into them.
+        let image_extent = self.context.render_target.image_extent();
+        let viewports = [vk::Viewport::default()
+            .width(image_extent.width as f32)
+            .height(image_extent.height as f32)
+            .max_depth(1.0)];
+        let scissors = [vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: image_extent,
+        }];
+        device.cmd_set_viewport(command_buffer, 0, &viewports);
+        device.cmd_set_scissor(command_buffer, 0, &scissors);
+
+        let aspect_ratio = image_extent.width as f32 / image_extent.height as f32;
+        let scene_uniforms = context::SceneUniforms {
+            view: camera.view(),
+            projection: camera.projection(aspect_ratio),
+            view_projection: camera.view_projection(aspect_ratio),
+            camera_position: camera.position.push(1.0),
+        };
+
+        self.resources
+            .write_buffer(frame.scene_buffer, bytes::as_bytes(&scene_uniforms))
+            .track()?;
+
+        context::DescriptorWriter::default()
+            .write_buffer(
+                context::SceneUniforms::BINDING,
+                vk::DescriptorType::UNIFORM_BUFFER,
+                frame.scene_buffer,
+                Default::default(),
+                context::SceneUniforms::SIZE,
+            )
+            .update(device, scene_set);
+
+        texture_sets
+            .iter()
+            .for_each(|(&texture_handle, &texture_set)| {
+                context::DescriptorWriter::default()
+                    .write_image(
+                        context::TextureBindings::BINDING,
+                        vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        self.resources.texture_view(texture_handle),
+                        self.context.texture_sampler,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    )
+                    .update(device, texture_set);
+            });
+
+        let mut bound_pipeline = vk::Pipeline::null();
+        objects.iter().for_each(|object| {
+            let pipeline = object.pipeline.unwrap_or(match object.texture_handle {
+                Some(_) => self.context.textured_mesh_pipeline,
+                None => self.context.mesh_pipeline,
+            });
+            let pipeline_handle = self.context.pipelines.get(pipeline);
 
-        (0..self.resources.allocated_buffers_len()).for_each(|_| {
-            self.resources.bind_buffers_per_draw(device, command_buffer);
-            device.cmd_draw_indexed(command_buffer, mesh.indices.len() as u32, 1, 0, 0, 0);
+            if pipeline_handle.pipeline != bound_pipeline {
+                device.cmd_bind_pipeline(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline_handle.pipeline,
+                );
+                pipeline_handle.dynamic_state.record(device, command_buffer);
+
+                // NOTE: Registered pipelines may not read the scene, their layout has no set for it then.
+                if pipeline_handle.set_layouts.len() > context::SceneUniforms::SET as usize {
+                    device.cmd_bind_descriptor_sets(
+                        command_buffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        pipeline_handle.pipeline_layout,
+                        context::SceneUniforms::SET,
+                        &[scene_set],
+                        &[],
+                    );
+                }
+
+                bound_pipeline = pipeline_handle.pipeline;
+            }
+
+            // NOTE: Pipelines without a texture set ignore the texture.
+            let texture_handle = object.texture_handle.filter(|_| {
+                pipeline_handle.set_layouts.len() > context::TextureBindings::SET as usize
+            });
+            if let Some(texture_handle) = texture_handle {
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline_handle.pipeline_layout,
+                    context::TextureBindings::SET,
+                    &[texture_sets[&texture_handle]],
+                    &[],
+                );
+            }
+
+            if !pipeline_handle.push_constant_stages.is_empty() {
+                let push_constants = context::MeshPushConstants {
+                    model: object.transform,
+                };
+
+                device.cmd_push_constants(
+                    command_buffer,
+                    pipeline_handle.pipeline_layout,
+                    pipeline_handle.push_constant_stages,
+                    Default::default(),
+                    bytes::as_bytes(&push_constants),
+                );
+            }
+
+            self.resources
+                .bind_mesh(device, command_buffer, object.mesh_handle);
+            device.cmd_draw_indexed(command_buffer, object.mesh_handle.index_count, 1, 0, 0, 0);
         });
 
         device.cmd_end_rendering(command_buffer);
-        device.end_command_buffer(command_buffer)?;
+
+        let (final_layout, final_stage_mask, final_access_mask) = match &self.context.render_target
+        {
+            context::RenderTarget::Swapchain(_) => (
+                vk::ImageLayout::PRESENT_SRC_KHR,
+                vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+                vk::AccessFlags2::NONE,
+            ),
+            context::RenderTarget::Offscreen(_) => (
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                vk::PipelineStageFlags2::COPY,
+                vk::AccessFlags2::TRANSFER_READ,
+            ),
+        };
+
+        let memory_barriers = [vk::ImageMemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+            .dst_stage_mask(final_stage_mask)
+            .dst_access_mask(final_access_mask)
+            .old_layout(vk::ImageLayout::ATTACHMENT_OPTIMAL)
+            .new_layout(final_layout)
+            .src_queue_family_index(queue_family_index)
+            .dst_queue_family_index(queue_family_index)
+            .image(image)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            })];
+
+        self.context.set_pipeline_barrier(
+            command_buffer,
+            &vk::DependencyInfo::default().image_memory_barriers(&memory_barriers),
+        );
+
+        if let context::RenderTarget::Offscreen(offscreen_target) = &self.context.render_target {
+            offscreen_target.record_readback(device, command_buffer);
+        }
+
+        device.end_command_buffer(command_buffer).track()?;
 
         let command_buffers = [command_buffer];
-        let signal_semaphores = [self.render_semaphore];
-        let wait_semaphores = [self.present_semaphore];
+        let signal_semaphores = [frame.render_semaphore];
+        let wait_semaphores = [frame.present_semaphore];
+        let wait_dst_stage_mask = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
 
-        let submit_info = vk::SubmitInfo::default()
-            .command_buffers(&command_buffers)
-            .signal_semaphores(&signal_semaphores)
-            .wait_semaphores(&wait_semaphores)
-            .wait_dst_stage_mask(&[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT]);
+        let queues = &self.context.device_handle.queues;
+        let queue_graphics = queues.graphics.queue;
 
-        let queue_graphics = self.context.device_handle.queue_graphics;
-        device.queue_submit(queue_graphics, &[submit_info], self.render_fence)?;
+        match &self.context.render_target {
+            context::RenderTarget::Swapchain(swapchain_handle) => {
+                let submit_info = vk::SubmitInfo::default()
+                    .command_buffers(&command_buffers)
+                    .signal_semaphores(&signal_semaphores)
+                    .wait_semaphores(&wait_semaphores)
+                    .wait_dst_stage_mask(&wait_dst_stage_mask);
+
+                device
+                    .queue_submit(queue_graphics, &[submit_info], frame.render_fence)
+                    .track()?;
+
+                let swapchains = [swapchain_handle.swapchain];
+                let image_indices = [image_index as u32];
+                let present_info = vk::PresentInfoKHR::default()
+                    .swapchains(&swapchains)
+                    .wait_semaphores(&signal_semaphores)
+                    .image_indices(&image_indices);
+
+                match swapchain_handle.swapchain_loader.queue_present(
+                    queues.present.unwrap_or(queues.graphics).queue,
+                    &present_info,
+                ) {
+                    Ok(false) => (),
+                    Ok(true) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                        self.is_swapchain_outdated = true
+                    }
+                    Err(error) => return Err(error).track(),
+                }
+            }
+            context::RenderTarget::Offscreen(_) => {
+                let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
+
+                device
+                    .queue_submit(queue_graphics, &[submit_info], frame.render_fence)
+                    .track()?;
+            }
+        }
+
+        self.frame_index = (self.frame_index + 1) % self.context.frames.len();
+
+        Ok(())
+    }
 
-        let swapchains = [self.context.swapchain_handle.swapchain];
-        let image_indices = [image_index as u32];
-        let present_info = vk::PresentInfoKHR::default()
-            .swapchains(&swapchains)
-            .wait_semaphores(&signal_semaphores)
-            .image_indices(&image_indices);
+    /// Waits for the last drawn frame and writes it into `path` as PNG or PPM.
+    ///
+    /// Fails for renderers not created with [`Renderer::new_headless`].
+    pub unsafe fn capture<P: AsRef<Path>>(&self, path: P) -> track::Result<()> {
+        let context::RenderTarget::Offscreen(offscreen_target) = &self.context.render_target else {
+            return Err(CaptureUnsupported).track();
+        };
+
+        let frames_count = self.context.frames.len();
+        let last_frame = &self.context.frames[(self.frame_index + frames_count - 1) % frames_count];
 
         self.context
-            .swapchain_handle
-            .swapchain_loader
-            .queue_present(queue_graphics, &present_info)?;
+            .wait_for_fences(&[last_frame.render_fence])
+            .track()?;
+
+        let pixels = self
+            .resources
+            .read_buffer(offscreen_target.readback_buffer)
+            .track()?;
+
+        context::write_image(path.as_ref(), offscreen_target.image_extent, &pixels).track()
+    }
+
+    /// Uploads the meshes into device-local memory through staging buffers
+    /// with a single submission for the whole batch.
+    pub fn upload_meshes(&mut self, meshes: &[mesh::Mesh]) -> track::Result<Vec<MeshHandle>> {
+        let mut staging_batch = resources::StagingBatch::default();
+
+        let mesh_handles = self.stage_meshes(&mut staging_batch, meshes).track()?;
+
+        self.submit_staging_batch(staging_batch).track()?;
+
+        Ok(mesh_handles)
+    }
+
+    /// Uploads the textures into sampled images with generated mip chains, ready for fragment shaders,
+    /// with a single submission for the whole batch.
+    pub fn upload_textures(
+        &mut self,
+        textures: &[texture::Texture],
+    ) -> track::Result<Vec<TextureHandle>> {
+        let mut staging_batch = resources::StagingBatch::default();
+
+        let texture_handles = self.stage_textures(&mut staging_batch, textures).track()?;
+
+        self.submit_staging_batch(staging_batch).track()?;
+
+        Ok(texture_handles)
+    }
+
+    /// Starts uploading the meshes and textures without waiting for the GPU.
+    ///
+    /// The returned handles must not be drawn while [`Renderer::is_uploading`], which is updated
+    /// by [`Renderer::poll_upload`]. Only one upload is streamed at a time.
+    pub fn begin_upload(
+        &mut self,
+        meshes: &[mesh::Mesh],
+        textures: &[texture::Texture],
+    ) -> track::Result<(Vec<MeshHandle>, Vec<TextureHandle>)> {
+        assert!(
+            !self.is_uploading(),
+            "Beginning an upload while another one is streamed"
+        );
+
+        let mut staging_batch = resources::StagingBatch::default();
+
+        let mesh_handles = self.stage_meshes(&mut staging_batch, meshes).track()?;
+        let texture_handles = self.stage_textures(&mut staging_batch, textures).track()?;
+
+        let device = &self.context.device_handle.device;
+        self.context
+            .mipmap_generator
+            .prepare(device, staging_batch.dst_images())
+            .track()?;
+
+        unsafe {
+            self.context
+                .upload_stream
+                .submit(
+                    device,
+                    &self.context.device_handle.queues,
+                    self.has_dedicated_transfer().then_some(|command_buffer| {
+                        self.record_staging_copies(&staging_batch, command_buffer)
+                    }),
+                    |command_buffer| self.record_staging_acquire(&staging_batch, command_buffer),
+                )
+                .track()?
+        };
+
+        self.streamed_upload = Some(staging_batch);
+
+        Ok((mesh_handles, texture_handles))
+    }
+
+    #[inline(always)]
+    pub fn is_uploading(&self) -> bool {
+        self.streamed_upload.is_some()
+    }
+
+    /// Finishes the streamed upload if the GPU is done with it, its resources may be drawn afterwards.
+    pub fn poll_upload(&mut self) -> track::Result<()> {
+        if !self.is_uploading() {
+            return Ok(());
+        }
+
+        let device = &self.context.device_handle.device;
+        if !unsafe { self.context.upload_stream.is_complete(device).track()? } {
+            return Ok(());
+        }
+
+        unsafe { self.context.upload_stream.reset(device).track()? };
+
+        self.finish_streamed_upload().track()
+    }
+
+    /// Builds the pipeline and registers it under `name`, replacing the one registered before.
+    ///
+    /// Objects are drawn with it by setting [`RenderObject::pipeline`] to the returned id: their mesh
+    /// vertex buffer is bound and its indices are drawn with `vkCmdDrawIndexed`. The scene uniforms
+    /// are bound at set 0, the texture at set 1 and the model matrix is pushed at offset 0,
+    /// each only if the shaders declare it.
+    #[inline(always)]
+    pub unsafe fn register_pipeline(
+        &mut self,
+        name: &str,
+        builder: PipelineBuilder,
+    ) -> track::Result<PipelineId> {
+        self.context.register_pipeline(name, builder).track()
+    }
+
+    #[inline(always)]
+    pub fn pipeline_id(&self, name: &str) -> Option<PipelineId> {
+        self.context.pipelines.id(name)
+    }
+
+    /// Creates the compute pipeline of the shader, replacing the one created before.
+    #[inline(always)]
+    pub unsafe fn register_compute_pipeline(
+        &mut self,
+        shader_name: &str,
+    ) -> track::Result<ComputePipelineId> {
+        self.context.register_compute_pipeline(shader_name).track()
+    }
+
+    #[inline(always)]
+    pub fn compute_pipeline_id(&self, shader_name: &str) -> Option<ComputePipelineId> {
+        self.context.pipelines.compute_id(shader_name)
+    }
+
+    /// Queues the dispatch, it runs before the next drawn frame renders and sees the dispatches
+    /// queued before it.
+    ///
+    /// Fails if the bindings or push constants don't match what the shader declares.
+    pub fn dispatch(&mut self, dispatch: ComputeDispatch) -> track::Result<()> {
+        dispatch
+            .check(self.context.pipelines.get_compute(dispatch.pipeline))
+            .track()?;
+
+        self.pending_dispatches.push(dispatch);
 
         Ok(())
     }
 
+    /// Creates a device-local buffer for compute shaders, also usable for indirect commands
+    /// and vertices. Its content is undefined until written.
+    #[inline(always)]
+    pub fn create_storage_buffer(&mut self, size: u64) -> track::Result<StorageBufferHandle> {
+        self.resources.create_storage_buffer(size).track()
+    }
+
+    /// Creates a device-local image for compute shaders, it stays in `GENERAL` layout and can also be
+    /// sampled in that layout. Its content is undefined until written.
+    pub fn create_storage_image(
+        &mut self,
+        image_extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> track::Result<StorageImageHandle> {
+        let device = &self.context.device_handle.device;
+        let storage_image_handle = self
+            .resources
+            .create_storage_image(device, image_extent, format)
+            .track()?;
+        let (image, _) = self.resources.storage_image(storage_image_handle);
+
+        unsafe {
+            self.context
+                .immediate_submit(|command_buffer| {
+                    let image_memory_barriers = [vk::ImageMemoryBarrier2::default()
+                        .dst_stage_mask(
+                            vk::PipelineStageFlags2::COMPUTE_SHADER
+                                | vk::PipelineStageFlags2::FRAGMENT_SHADER,
+                        )
+                        .dst_access_mask(
+                            vk::AccessFlags2::SHADER_STORAGE_READ
+                                | vk::AccessFlags2::SHADER_STORAGE_WRITE
+                                | vk::AccessFlags2::SHADER_SAMPLED_READ,
+                        )
+                        .old_layout(vk::ImageLayout::UNDEFINED)
+                        .new_layout(vk::ImageLayout::GENERAL)
+                        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .image(image)
+                        .subresource_range(resources::StorageImage::SUBRESOURCE_RANGE)];
+
+                    self.context.set_pipeline_barrier(
+                        command_buffer,
+                        &vk::DependencyInfo::default()
+                            .image_memory_barriers(&image_memory_barriers),
+                    );
+                })
+                .track()?;
+        }
+
+        Ok(storage_image_handle)
+    }
+
+    /// Schedules the storage buffer for destruction once the frames in flight are done with it.
+    #[inline]
+    pub fn release_storage_buffer(&mut self, storage_buffer_handle: StorageBufferHandle) {
+        self.retired_resources.push((
+            self.context.frames.len(),
+            RetiredResource::StorageBuffer(storage_buffer_handle),
+        ));
+    }
+
+    /// Schedules the storage image for destruction once the frames in flight are done with it.
+    #[inline]
+    pub fn release_storage_image(&mut self, storage_image_handle: StorageImageHandle) {
+        self.retired_resources.push((
+            self.context.frames.len(),
+            RetiredResource::StorageImage(storage_image_handle),
+        ));
+    }
+
+    /// Schedules the buffers of the mesh for destruction once the frames in flight are done with them.
+    #[inline]
+    pub fn release_mesh(&mut self, mesh_handle: MeshHandle) {
+        self.retired_resources.push((
+            self.context.frames.len(),
+            RetiredResource::Mesh(mesh_handle),
+        ));
+    }
+
+    /// Schedules the image of the texture for destruction once the frames in flight are done with it.
+    #[inline]
+    pub fn release_texture(&mut self, texture_handle: TextureHandle) {
+        self.retired_resources.push((
+            self.context.frames.len(),
+            RetiredResource::Texture(texture_handle),
+        ));
+    }
+
+    /// Counts the frame fence that was just waited and destroys the resources no frame in flight uses anymore.
+    ///
+    /// Fences are signaled in submission order, so after waiting as many of them as there are frames
+    /// every frame recorded before the release is done.
+    fn destroy_retired_resources(&mut self) {
+        // NOTE: Released resources may still be the destination of the streamed upload.
+        let is_uploading = self.is_uploading();
+        let device = &self.context.device_handle.device;
+        let resources = &mut self.resources;
+
+        self.retired_resources
+            .retain_mut(|(fences_left, retired_resource)| {
+                *fences_left = fences_left.saturating_sub(1);
+                if *fences_left > 0 || is_uploading {
+                    return true;
+                }
+
+                unsafe {
+                    match *retired_resource {
+                        RetiredResource::Mesh(mesh_handle) => resources.destroy_mesh(mesh_handle),
+                        RetiredResource::Texture(texture_handle) => {
+                            resources.destroy_texture(device, texture_handle)
+                        }
+                        RetiredResource::StorageBuffer(storage_buffer_handle) => {
+                            resources.destroy_storage_buffer(storage_buffer_handle)
+                        }
+                        RetiredResource::StorageImage(storage_image_handle) => {
+                            resources.destroy_storage_image(device, storage_image_handle)
+                        }
+                    }
+                }
+
+                false
+            });
+    }
+
+    /// Records the queued dispatches ahead of the frame's graphics work.
+    ///
+    /// Every dispatch sees the writes of the previous ones, the graphics work sees all of them
+    /// whether it reads them as indirect commands, vertices or from shaders.
+    unsafe fn record_dispatches(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        compute_sets: &[Option<vk::DescriptorSet>],
+    ) {
+        let device = &self.context.device_handle.device;
+
+        let graphics_read_stages = vk::PipelineStageFlags2::DRAW_INDIRECT
+            | vk::PipelineStageFlags2::INDEX_INPUT
+            | vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT
+            | vk::PipelineStageFlags2::VERTEX_SHADER
+            | vk::PipelineStageFlags2::FRAGMENT_SHADER;
+        let compute_accesses = vk::AccessFlags2::SHADER_STORAGE_READ
+            | vk::AccessFlags2::SHADER_STORAGE_WRITE
+            | vk::AccessFlags2::INDIRECT_COMMAND_READ;
+
+        // NOTE: Previous frames in flight may still read what the dispatches are about to overwrite.
+        let memory_barriers = [vk::MemoryBarrier2::default()
+            .src_stage_mask(graphics_read_stages | vk::PipelineStageFlags2::COMPUTE_SHADER)
+            .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+            .dst_stage_mask(
+                vk::PipelineStageFlags2::COMPUTE_SHADER | vk::PipelineStageFlags2::DRAW_INDIRECT,
+            )
+            .dst_access_mask(compute_accesses)];
+        self.context.set_pipeline_barrier(
+            command_buffer,
+            &vk::DependencyInfo::default().memory_barriers(&memory_barriers),
+        );
+
+        for (index, (dispatch, &compute_set)) in
+            self.pending_dispatches.iter().zip(compute_sets).enumerate()
+        {
+            if index > 0 {
+                let memory_barriers = [vk::MemoryBarrier2::default()
+                    .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                    .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                    .dst_stage_mask(
+                        vk::PipelineStageFlags2::COMPUTE_SHADER
+                            | vk::PipelineStageFlags2::DRAW_INDIRECT,
+                    )
+                    .dst_access_mask(compute_accesses)];
+                self.context.set_pipeline_barrier(
+                    command_buffer,
+                    &vk::DependencyInfo::default().memory_barriers(&memory_barriers),
+                );
+            }
+
+            // NOTE: The pipeline may have been reloaded with another layout since it was queued.
+            let pipeline_handle = self.context.pipelines.get_compute(dispatch.pipeline);
+            if let Err(error) = dispatch.check(pipeline_handle) {
+                warn!("Skipping a dispatch that no longer matches its reloaded shader: {error}");
+                continue;
+            }
+
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                pipeline_handle.pipeline,
+            );
+
+            if let Some(compute_set) = compute_set {
+                dispatch
+                    .bindings
+                    .iter()
+                    .fold(
+                        context::DescriptorWriter::default(),
+                        |descriptor_writer, &(binding, compute_binding)| match compute_binding {
+                            ComputeBinding::StorageBuffer(storage_buffer_handle) => {
+                                descriptor_writer.write_buffer(
+                                    binding,
+                                    vk::DescriptorType::STORAGE_BUFFER,
+                                    self.resources.storage_buffer(storage_buffer_handle),
+                                    Default::default(),
+                                    vk::WHOLE_SIZE,
+                                )
+                            }
+                            ComputeBinding::StorageImage(storage_image_handle) => descriptor_writer
+                                .write_image(
+                                    binding,
+                                    vk::DescriptorType::STORAGE_IMAGE,
+                                    self.resources.storage_image(storage_image_handle).1,
+                                    vk::Sampler::null(),
+                                    vk::ImageLayout::GENERAL,
+                                ),
+                        },
+                    )
+                    .update(device, compute_set);
+
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::COMPUTE,
+                    pipeline_handle.pipeline_layout,
+                    Default::default(),
+                    &[compute_set],
+                    &[],
+                );
+            }
+
+            // NOTE: Only the range the shader reads is pushed, the bytes before it are padding.
+            if let Some(range) = pipeline_handle.push_constant_range {
+                device.cmd_push_constants(
+                    command_buffer,
+                    pipeline_handle.pipeline_layout,
+                    pipeline_handle.push_constant_stages,
+                    range.offset,
+                    &dispatch.push_constants[range.offset as usize..],
+                );
+            }
+
+            match dispatch.size {
+                DispatchSize::Groups([group_count_x, group_count_y, group_count_z]) => {
+                    device.cmd_dispatch(command_buffer, group_count_x, group_count_y, group_count_z)
+                }
+                DispatchSize::Invocations(invocations) => {
+                    let [group_count_x, group_count_y, group_count_z] =
+                        pipeline_handle.group_counts(invocations);
+                    device.cmd_dispatch(command_buffer, group_count_x, group_count_y, group_count_z)
+                }
+                DispatchSize::Indirect { buffer, offset } => device.cmd_dispatch_indirect(
+                    command_buffer,
+                    self.resources.storage_buffer(buffer),
+                    offset,
+                ),
+            }
+        }
+
+        let memory_barriers = [vk::MemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+            .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+            .dst_stage_mask(graphics_read_stages)
+            .dst_access_mask(
+                vk::AccessFlags2::INDIRECT_COMMAND_READ
+                    | vk::AccessFlags2::INDEX_READ
+                    | vk::AccessFlags2::VERTEX_ATTRIBUTE_READ
+                    | vk::AccessFlags2::SHADER_READ,
+            )];
+        self.context.set_pipeline_barrier(
+            command_buffer,
+            &vk::DependencyInfo::default().memory_barriers(&memory_barriers),
+        );
+    }
+
+    fn stage_meshes(
+        &mut self,
+        staging_batch: &mut resources::StagingBatch,
+        meshes: &[mesh::Mesh],
+    ) -> track::Result<Vec<MeshHandle>> {
+        meshes
+            .iter()
+            .map(|mesh| self.resources.uplaod_mesh(staging_batch, mesh))
+            .collect()
+    }
+
+    fn stage_textures(
+        &mut self,
+        staging_batch: &mut resources::StagingBatch,
+        textures: &[texture::Texture],
+    ) -> track::Result<Vec<TextureHandle>> {
+        let device = &self.context.device_handle.device;
+        let image_requirements = self.context.mipmap_generator.image_requirements();
+
+        textures
+            .iter()
+            .map(|texture| {
+                self.resources
+                    .upload_texture(device, staging_batch, texture, image_requirements)
+            })
+            .collect()
+    }
+
     #[inline(always)]
-    pub fn upload_asset<P: AsRef<Path> + std::fmt::Debug>(
+    fn has_dedicated_transfer(&self) -> bool {
+        let queues = &self.context.device_handle.queues;
+
+        queues.transfer.family_index != queues.graphics.family_index
+    }
+
+    /// Transfers the staged data on the transfer queue, generates the mip chains of the images
+    /// on the graphics one, waits for it and frees the staging buffers.
+    fn submit_staging_batch(
         &mut self,
-        path: P,
-    ) -> track::Result<mesh::Mesh> {
-        let mesh = mesh::Mesh::new(path).track()?;
-        self.resources.uplaod_mesh(&mesh).track()?;
+        staging_batch: resources::StagingBatch,
+    ) -> track::Result<()> {
+        // NOTE: The streamed upload shares the mip chain generator.
+        if self.is_uploading() {
+            unsafe {
+                self.context
+                    .upload_stream
+                    .wait(&self.context.device_handle.device)
+                    .track()?
+            };
+            self.finish_streamed_upload().track()?;
+        }
+
+        if !staging_batch.is_empty() {
+            let device = &self.context.device_handle.device;
+
+            self.context
+                .mipmap_generator
+                .prepare(device, staging_batch.dst_images())
+                .track()?;
+
+            unsafe {
+                if self.has_dedicated_transfer() {
+                    self.context
+                        .transfer_submit(|command_buffer| {
+                            self.record_staging_copies(&staging_batch, command_buffer)
+                        })
+                        .track()?;
+                }
+
+                self.context
+                    .immediate_submit(|command_buffer| {
+                        self.record_staging_acquire(&staging_batch, command_buffer)
+                    })
+                    .track()?;
+            }
+
+            self.context.mipmap_generator.finish(device).track()?;
+        }
+
+        self.resources.finish_upload(staging_batch);
 
-        Ok(mesh)
+        Ok(())
+    }
+
+    /// Frees what the completed streamed upload needed.
+    fn finish_streamed_upload(&mut self) -> track::Result<()> {
+        if let Some(staging_batch) = self.streamed_upload.take() {
+            self.context
+                .mipmap_generator
+                .finish(&self.context.device_handle.device)
+                .track()?;
+            self.resources.finish_upload(staging_batch);
+        }
+
+        Ok(())
+    }
+
+    /// Records the copies of the batch on a dedicated transfer family and releases the ownership
+    /// of its resources to the graphics family.
+    unsafe fn record_staging_copies(
+        &self,
+        staging_batch: &resources::StagingBatch,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        let device = &self.context.device_handle.device;
+        let (buffer_barriers, image_barriers) = self.ownership_transfers(staging_batch);
+        let release_barriers: SmallVec<[_; 8]> = buffer_barriers
+            .iter()
+            .map(|(release_barrier, _)| *release_barrier)
+            .collect();
+        let image_release_barriers: SmallVec<[_; 8]> = image_barriers
+            .iter()
+            .map(|(release_barrier, _)| *release_barrier)
+            .collect();
+
+        staging_batch.record_copies(device, command_buffer);
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo::default()
+                .buffer_memory_barriers(&release_barriers)
+                .image_memory_barriers(&image_release_barriers),
+        );
+    }
+
+    /// Records what the graphics family does with the batch: the copies themselves unless there is
+    /// a dedicated transfer family, otherwise the ownership acquisition, then the mip chain generation.
+    unsafe fn record_staging_acquire(
+        &self,
+        staging_batch: &resources::StagingBatch,
+        command_buffer: vk::CommandBuffer,
+    ) {
+        let device = &self.context.device_handle.device;
+
+        if self.has_dedicated_transfer() {
+            let (buffer_barriers, image_barriers) = self.ownership_transfers(staging_batch);
+            let acquire_barriers: SmallVec<[_; 8]> = buffer_barriers
+                .iter()
+                .map(|(_, acquire_barrier)| *acquire_barrier)
+                .collect();
+            let image_acquire_barriers: SmallVec<[_; 8]> = image_barriers
+                .iter()
+                .map(|(_, acquire_barrier)| *acquire_barrier)
+                .collect();
+
+            device.cmd_pipeline_barrier2(
+                command_buffer,
+                &vk::DependencyInfo::default()
+                    .buffer_memory_barriers(&acquire_barriers)
+                    .image_memory_barriers(&image_acquire_barriers),
+            );
+        } else {
+            staging_batch.record(device, command_buffer);
+        }
+
+        self.context
+            .mipmap_generator
+            .record(device, command_buffer, staging_batch.dst_images());
+    }
+
+    /// Release and acquire barriers of every resource in the batch.
+    ///
+    /// Resources are written by the dedicated transfer family and used by the graphics one,
+    /// so their ownership is released after the copies and acquired before any other use.
+    #[allow(clippy::type_complexity)]
+    fn ownership_transfers(
+        &self,
+        staging_batch: &resources::StagingBatch,
+    ) -> (
+        SmallVec<
+            [(
+                vk::BufferMemoryBarrier2<'static>,
+                vk::BufferMemoryBarrier2<'static>,
+            ); 8],
+        >,
+        SmallVec<
+            [(
+                vk::ImageMemoryBarrier2<'static>,
+                vk::ImageMemoryBarrier2<'static>,
+            ); 8],
+        >,
+    ) {
+        let queues = &self.context.device_handle.queues;
+        let transfer_family_index = queues.transfer.family_index;
+        let graphics_family_index = queues.graphics.family_index;
+
+        let buffer_barriers = staging_batch
+            .dst_buffers()
+            .map(|buffer| {
+                context::QueueManager::buffer_ownership_transfer(
+                    buffer,
+                    (
+                        transfer_family_index,
+                        vk::PipelineStageFlags2::COPY,
+                        vk::AccessFlags2::TRANSFER_WRITE,
+                    ),
+                    (
+                        graphics_family_index,
+                        vk::PipelineStageFlags2::VERTEX_INPUT,
+                        vk::AccessFlags2::VERTEX_ATTRIBUTE_READ | vk::AccessFlags2::INDEX_READ,
+                    ),
+                )
+            })
+            .collect();
+
+        // NOTE: Images stay in the transfer layout, the mip chain generation takes them from there.
+        let image_barriers = staging_batch
+            .dst_images()
+            .map(|staged_image| {
+                context::QueueManager::image_ownership_transfer(
+                    staged_image.image,
+                    staged_image.subresource_range(),
+                    (
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    ),
+                    (
+                        transfer_family_index,
+                        vk::PipelineStageFlags2::COPY,
+                        vk::AccessFlags2::TRANSFER_WRITE,
+                    ),
+                    (
+                        graphics_family_index,
+                        vk::PipelineStageFlags2::COPY,
+                        vk::AccessFlags2::TRANSFER_WRITE,
+                    ),
+                )
+            })
+            .collect();
+
+        (buffer_barriers, image_barriers)
     }
 }
 
@@ -219,36 +1290,53 @@ impl Drop for Renderer {
             let device = &context.device_handle.device;
             device.device_wait_idle().unwrap();
 
-            device.destroy_command_pool(context.command.command_pool, None);
             context
-                .swapchain_handle
-                .image_views
+                .frames
                 .iter()
-                .for_each(|image_view| {
-                    device.destroy_image_view(*image_view, None);
-                });
+                .for_each(|frame| frame.destroy(device));
+
+            match &context.render_target {
+                context::RenderTarget::Swapchain(swapchain_handle) => {
+                    swapchain_handle.destroy(device);
+                }
+                context::RenderTarget::Offscreen(offscreen_target) => {
+                    device.destroy_image_view(offscreen_target.image_view, None);
+                }
+            }
             device.destroy_image_view(context.depth_buffer.image_view, None);
 
-            context
-                .swapchain_handle
-                .swapchain_loader
-                .destroy_swapchain(context.swapchain_handle.swapchain, None);
+            if let Some(staging_batch) = self.streamed_upload.take() {
+                self.resources.finish_upload(staging_batch);
+            }
 
-            device.destroy_pipeline(context.pipeline_handle.pipeline, None);
-            device.destroy_pipeline_layout(context.pipeline_handle.pipeline_layout, None);
+            context.pipelines.destroy(device);
+            context.mipmap_generator.destroy(device);
 
-            device.destroy_fence(self.render_fence, None);
-            device.destroy_semaphore(self.present_semaphore, None);
-            device.destroy_semaphore(self.render_semaphore, None);
+            // NOTE: Failing to save the cache only slows the next startup down.
+            if let Err(error) = context
+                .pipeline_cache
+                .save(device, &paths::pipeline_cache_path())
+            {
+                warn!("Failed to save the pipeline cache: {error}");
+            }
+            context.pipeline_cache.destroy(device);
 
+            context.descriptor_layout_cache.destroy(device);
+            context.sampler_cache.destroy(device);
+            context.immediate_submit.destroy(device);
+            context.transfer_submit.destroy(device);
+            context.upload_stream.destroy(device);
+
+            self.resources.destroy_image_views(device);
             ManuallyDrop::drop(&mut self.resources);
 
             device.destroy_device(None);
 
-            context
-                .surface_handle
-                .surface_loader
-                .destroy_surface(context.surface_handle.surface, None);
+            if let Some(surface_handle) = &context.surface_handle {
+                surface_handle
+                    .surface_loader
+                    .destroy_surface(surface_handle.surface, None);
+            }
 
             #[cfg(feature = "validation")]
             context
@@ -260,3 +1348,46 @@ impl Drop for Renderer {
         }
     }
 }
+
+/// Headless renderer asked for an image without pixels.
+#[derive(Debug)]
+pub struct EmptyHeadlessExtent(vk::Extent2D);
+
+impl std::fmt::Display for EmptyHeadlessExtent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Headless image extent {}x{} is empty",
+            self.0.width, self.0.height
+        )
+    }
+}
+
+impl std::error::Error for EmptyHeadlessExtent {}
+
+/// Compute dispatch whose bindings or push constants don't match its shader.
+#[derive(Debug)]
+pub struct InvalidDispatch(String);
+
+impl std::fmt::Display for InvalidDispatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid compute dispatch: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidDispatch {}
+
+/// Frame capture requested from a renderer drawing into a window.
+#[derive(Debug)]
+pub struct CaptureUnsupported;
+
+impl std::fmt::Display for CaptureUnsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Capturing frames is supported only by a headless renderer"
+        )
+    }
+}
+
+impl std::error::Error for CaptureUnsupported {}
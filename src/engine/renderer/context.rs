@@ -1,49 +1,140 @@
 mod command;
 mod debug;
 mod depth;
+mod descriptor;
 mod device;
+mod frame;
+#[cfg(feature = "hot_reload")]
+mod hot_reload;
+mod immediate;
 mod instance;
+mod mipmap;
+mod offscreen;
 mod pipeline;
+mod pipeline_cache;
+mod queue;
+mod reflect;
+mod sampler;
 mod shader;
+mod stream;
 mod surface;
 mod swapchain;
 
 use ash::prelude::VkResult;
 use ash::vk;
+use smallvec::SmallVec;
+#[cfg(feature = "hot_reload")]
+use tracing::warn;
 use track::Context as TrackContext; // Renamed the `Context`'s name due to name collision with backend's `Context`.
 
 use self::device::DeviceHandle;
 use self::surface::SurfaceHandle;
 use self::swapchain::SwapchainHandle;
 
+pub use self::descriptor::DescriptorWriter;
+pub use self::frame::DEFAULT_FRAMES_IN_FLIGHT;
+pub use self::offscreen::{write_image, OffscreenTarget};
+pub use self::pipeline::{
+    BlendMode, ComputePipelineHandle, ComputePipelineId, MeshPushConstants, PipelineBuilder,
+    PipelineId, SceneUniforms, TextureBindings,
+};
+pub use self::queue::QueueManager;
+
 use super::resources;
+use crate::engine::utils::paths;
+
+/// Where the rendered frames end up.
+pub enum RenderTarget {
+    Swapchain(SwapchainHandle),
+    Offscreen(OffscreenTarget),
+}
+
+impl RenderTarget {
+    #[inline(always)]
+    pub fn image_extent(&self) -> vk::Extent2D {
+        match self {
+            Self::Swapchain(swapchain_handle) => swapchain_handle.image_extent,
+            Self::Offscreen(offscreen_target) => offscreen_target.image_extent,
+        }
+    }
+}
+
 pub struct Context {
     #[cfg(feature = "validation")]
     pub debug_handle: debug::DebugHandle,
-    pub surface_handle: SurfaceHandle,
+    pub surface_handle: Option<SurfaceHandle>,
     pub device_handle: DeviceHandle,
-    pub swapchain_handle: SwapchainHandle,
-    pub pipeline_handle: pipeline::PipelineHandle,
-    pub command: command::Command,
+    pub render_target: RenderTarget,
+    pub pipeline_cache: pipeline_cache::PipelineCache,
+    pub pipelines: pipeline::PipelineRegistry,
+    /// Pipeline of the objects without a texture.
+    pub mesh_pipeline: PipelineId,
+    /// Pipeline of the objects with a texture.
+    pub textured_mesh_pipeline: PipelineId,
+    pub descriptor_layout_cache: descriptor::DescriptorLayoutCache,
+    pub sampler_cache: sampler::SamplerCache,
+    pub texture_sampler: vk::Sampler,
+    pub mipmap_generator: mipmap::MipmapGenerator,
+    pub frames: SmallVec<[frame::Frame; frame::MAX_FRAMES_IN_FLIGHT]>,
+    pub immediate_submit: immediate::ImmediateSubmit,
+    pub transfer_submit: immediate::ImmediateSubmit,
+    pub upload_stream: stream::UploadStream,
+    /// Recompiles the changed shaders in development builds, `None` if the directory can't be watched.
+    #[cfg(feature = "hot_reload")]
+    pub shader_watcher: Option<hot_reload::ShaderWatcher>,
     pub instance_handle: instance::InstaceHandle,
     pub depth_buffer: depth::DepthBuffer,
 }
 
 // TODO: `debug!` message of every stage of Vulkan initialization
 impl Context {
-    pub fn new(window: &winit::window::Window) -> track::Result<(Self, resources::Resources)> {
+    pub const MESH_PIPELINE: &str = "mesh";
+    pub const TEXTURED_MESH_PIPELINE: &str = "textured_mesh";
+
+    #[inline(always)]
+    pub fn new(
+        window: &winit::window::Window,
+        frames_in_flight: usize,
+    ) -> track::Result<(Self, resources::Resources)> {
+        Self::create(Some(window), Default::default(), frames_in_flight)
+    }
+
+    /// Creates a context without a surface, rendering into an [`OffscreenTarget`] of the given size.
+    #[inline(always)]
+    pub fn new_headless(
+        image_extent: vk::Extent2D,
+        frames_in_flight: usize,
+    ) -> track::Result<(Self, resources::Resources)> {
+        Self::create(None, image_extent, frames_in_flight)
+    }
+
+    fn create(
+        window: Option<&winit::window::Window>,
+        headless_image_extent: vk::Extent2D,
+        frames_in_flight: usize,
+    ) -> track::Result<(Self, resources::Resources)> {
+        assert!(
+            (1..=frame::MAX_FRAMES_IN_FLIGHT).contains(&frames_in_flight),
+            "Frames in flight must be in 1..={}, got {frames_in_flight}",
+            frame::MAX_FRAMES_IN_FLIGHT
+        );
+
         let instance_handle = instance::InstaceHandle::new(window).track()?;
 
         #[cfg(feature = "validation")]
         let debug_handle =
             debug::DebugHandle::new(&instance_handle.entry, &instance_handle.instance).track()?;
 
-        let surface_handle =
-            SurfaceHandle::new(&instance_handle.entry, &instance_handle.instance, window)
-                .track()?;
+        let surface_handle = match window {
+            Some(window) => Some(
+                SurfaceHandle::new(&instance_handle.entry, &instance_handle.instance, window)
+                    .track()?,
+            ),
+            None => None,
+        };
 
         let device_handle =
-            DeviceHandle::new(&instance_handle.instance, &surface_handle).track()?;
+            DeviceHandle::new(&instance_handle.instance, surface_handle.as_ref()).track()?;
 
         let mut resources = resources::Resources::new(
             &instance_handle.instance,
@@ -52,52 +143,114 @@ impl Context {
         )
         .track()?;
 
-        let swapchain_handle = swapchain::SwapchainHandle::new(
-            &instance_handle.instance,
-            &device_handle,
-            &surface_handle,
-            window,
-        )
-        .track()?;
+        let render_target = match (window, &surface_handle) {
+            (Some(window), Some(surface_handle)) => RenderTarget::Swapchain(
+                swapchain::SwapchainHandle::new(
+                    &instance_handle.instance,
+                    &device_handle,
+                    surface_handle,
+                    vk::Extent2D {
+                        width: window.inner_size().width,
+                        height: window.inner_size().height,
+                    },
+                )
+                .track()?,
+            ),
+            _ => RenderTarget::Offscreen(
+                OffscreenTarget::new(
+                    &device_handle.device,
+                    &mut resources,
+                    device_handle.surface_format.format,
+                    headless_image_extent,
+                )
+                .track()?,
+            ),
+        };
+        let image_extent = render_target.image_extent();
 
         let depth_buffer = depth::DepthBuffer::new(
             &device_handle.device,
             &mut resources,
             vk::Extent3D {
-                width: swapchain_handle.image_extent.width,
-                height: swapchain_handle.image_extent.height,
+                width: image_extent.width,
+                height: image_extent.height,
                 depth: 1,
             },
         )
         .track()?;
 
-        let shader_handle = shader::ShaderHandle::new(&device_handle.device);
+        let mut descriptor_layout_cache = descriptor::DescriptorLayoutCache::default();
+        let pipeline_cache = pipeline_cache::PipelineCache::new(
+            &device_handle.device,
+            &device_handle.device_properties,
+            &paths::pipeline_cache_path(),
+        )
+        .track()?;
+
+        let mut pipelines = pipeline::PipelineRegistry::new(pipeline_cache.pipeline_cache);
+        let mesh_pipeline = pipelines
+            .register(
+                &device_handle.device,
+                &mut descriptor_layout_cache,
+                Self::MESH_PIPELINE,
+                PipelineBuilder::new(Self::MESH_PIPELINE),
+                device_handle.surface_format.format,
+            )
+            .track()?;
+        let textured_mesh_pipeline = pipelines
+            .register(
+                &device_handle.device,
+                &mut descriptor_layout_cache,
+                Self::TEXTURED_MESH_PIPELINE,
+                PipelineBuilder::new(Self::TEXTURED_MESH_PIPELINE),
+                device_handle.surface_format.format,
+            )
+            .track()?;
 
-        let pipeline_handle = pipeline::PipelineHandle::new(
+        let mipmap_generator = mipmap::MipmapGenerator::new(
+            &instance_handle.instance,
+            device_handle.physical_device,
             &device_handle.device,
-            &shader_handle,
-            device_handle.surface_format.format,
-            swapchain_handle.image_extent,
+            pipeline_cache.pipeline_cache,
+            &mut descriptor_layout_cache,
         )
         .track()?;
 
-        shader_handle
-            .shader_modules
-            .iter()
-            .for_each(|(shader_module, _)| {
-                unsafe {
-                    device_handle
-                        .device
-                        .destroy_shader_module(*shader_module, None)
-                };
-            });
-
-        let command = command::Command::new(
+        let mut sampler_cache = sampler::SamplerCache::default();
+        let texture_sampler = sampler_cache
+            .get_or_create(
+                &device_handle.device,
+                sampler::SamplerDescription::LINEAR_REPEAT,
+            )
+            .track()?;
+
+        let frames = (0..frames_in_flight)
+            .map(|_| {
+                frame::Frame::new(
+                    &device_handle.device,
+                    &mut resources,
+                    device_handle.queues.graphics.family_index,
+                )
+            })
+            .collect::<track::Result<SmallVec<_>>>()?;
+
+        let immediate_submit = immediate::ImmediateSubmit::new(
             &device_handle.device,
-            device_handle.queue_family_index,
-            swapchain_handle.images.len() as u32,
+            device_handle.queues.graphics.family_index,
         )
         .track()?;
+        let transfer_submit = immediate::ImmediateSubmit::new(
+            &device_handle.device,
+            device_handle.queues.transfer.family_index,
+        )
+        .track()?;
+        let upload_stream =
+            stream::UploadStream::new(&device_handle.device, &device_handle.queues).track()?;
+
+        #[cfg(feature = "hot_reload")]
+        let shader_watcher = hot_reload::ShaderWatcher::new()
+            .map_err(|error| warn!("Shader hot reloading is disabled: {error}"))
+            .ok();
 
         Ok((
             Self {
@@ -106,71 +259,225 @@ impl Context {
                 debug_handle,
                 surface_handle,
                 device_handle,
-                swapchain_handle,
+                render_target,
                 depth_buffer,
-                pipeline_handle,
-                command,
+                pipeline_cache,
+                pipelines,
+                mesh_pipeline,
+                textured_mesh_pipeline,
+                descriptor_layout_cache,
+                sampler_cache,
+                texture_sampler,
+                mipmap_generator,
+                frames,
+                immediate_submit,
+                transfer_submit,
+                upload_stream,
+                #[cfg(feature = "hot_reload")]
+                shader_watcher,
             },
             resources,
         ))
     }
 
-    #[inline]
-    pub unsafe fn create_fence(
-        &self,
-        fence_info: &vk::FenceCreateInfo,
-    ) -> track::Result<vk::Fence> {
+    /// Layout of the set holding [`SceneUniforms`], the same in every mesh pipeline.
+    #[inline(always)]
+    pub fn scene_set_layout(&self) -> vk::DescriptorSetLayout {
+        self.pipelines.get(self.textured_mesh_pipeline).set_layouts[SceneUniforms::SET as usize]
+    }
+
+    /// Layout of the set holding the texture of [`TextureBindings`].
+    #[inline(always)]
+    pub fn texture_set_layout(&self) -> vk::DescriptorSetLayout {
+        self.pipelines.get(self.textured_mesh_pipeline).set_layouts[TextureBindings::SET as usize]
+    }
+
+    /// Builds the pipeline and registers it under `name`, replacing the one registered before.
+    pub unsafe fn register_pipeline(
+        &mut self,
+        name: &str,
+        builder: PipelineBuilder,
+    ) -> track::Result<PipelineId> {
+        let device = &self.device_handle.device;
+
+        // NOTE: Frames in flight may still draw with the replaced pipeline.
+        if self.pipelines.id(name).is_some() {
+            device.device_wait_idle().track()?;
+        }
+
+        self.pipelines.register(
+            device,
+            &mut self.descriptor_layout_cache,
+            name,
+            builder,
+            self.device_handle.surface_format.format,
+        )
+    }
+
+    /// Creates the compute pipeline of the shader, replacing the one created before.
+    pub unsafe fn register_compute_pipeline(
+        &mut self,
+        shader_name: &str,
+    ) -> track::Result<ComputePipelineId> {
+        let device = &self.device_handle.device;
+
+        // NOTE: Frames in flight may still dispatch the replaced pipeline.
+        if self.pipelines.compute_id(shader_name).is_some() {
+            device.device_wait_idle().track()?;
+        }
+
+        self.pipelines
+            .register_compute(device, &mut self.descriptor_layout_cache, shader_name)
+    }
+
+    /// Rebuilds the swapchain together with everything that depends on its extent:
+    /// image views and the depth buffer. Pipelines take the viewport from the command buffer,
+    /// so they are kept.
+    ///
+    /// Does nothing for an offscreen target.
+    pub unsafe fn recreate_swapchain(
+        &mut self,
+        resources: &mut resources::Resources,
+        window_extent: vk::Extent2D,
+    ) -> track::Result<()> {
+        let (RenderTarget::Swapchain(swapchain_handle), Some(surface_handle)) =
+            (&mut self.render_target, &self.surface_handle)
+        else {
+            return Ok(());
+        };
+
+        let device = &self.device_handle.device;
+        device.device_wait_idle().track()?;
+
         self.device_handle
-            .device
-            .create_fence(fence_info, None)
-            .track()
+            .update_surface_capabilities(surface_handle)
+            .track()?;
+
+        swapchain_handle
+            .recreate(&self.device_handle, surface_handle, window_extent)
+            .track()?;
+        let image_extent = swapchain_handle.image_extent;
+
+        let device = &self.device_handle.device;
+
+        self.depth_buffer.destroy(device, resources);
+        self.depth_buffer = depth::DepthBuffer::new(
+            device,
+            resources,
+            vk::Extent3D {
+                width: image_extent.width,
+                height: image_extent.height,
+                depth: 1,
+            },
+        )
+        .track()?;
+
+        Ok(())
+    }
+
+    /// Recompiles the shaders changed since the last call and rebuilds the pipelines using them.
+    ///
+    /// A pipeline whose shaders fail to compile or link stays as it was, the error is logged.
+    #[cfg(feature = "hot_reload")]
+    pub unsafe fn reload_changed_shaders(&mut self) -> track::Result<()> {
+        let Some(shader_watcher) = &self.shader_watcher else {
+            return Ok(());
+        };
+
+        let shader_names = shader_watcher.recompile_changed();
+        if shader_names.is_empty() {
+            return Ok(());
+        }
+
+        let device = &self.device_handle.device;
+        device.device_wait_idle().track()?;
+
+        self.pipelines.rebuild_with_shaders(
+            device,
+            &mut self.descriptor_layout_cache,
+            self.device_handle.surface_format.format,
+            &shader_names,
+        );
+
+        Ok(())
     }
 
-    #[inline]
-    pub unsafe fn create_semaphore(
+    /// Records and submits one-shot commands on the graphics queue, waiting for their completion.
+    #[inline(always)]
+    pub unsafe fn immediate_submit<F: FnOnce(vk::CommandBuffer)>(
         &self,
-        semaphore_info: &vk::SemaphoreCreateInfo,
-    ) -> track::Result<vk::Semaphore> {
-        self.device_handle
-            .device
-            .create_semaphore(semaphore_info, None)
-            .track()
+        record: F,
+    ) -> track::Result<()> {
+        self.immediate_submit.submit(
+            &self.device_handle.device,
+            self.device_handle.queues.graphics.queue,
+            record,
+        )
     }
 
+    /// Same as [`Context::immediate_submit`] on the transfer queue, which may be a dedicated one.
     #[inline(always)]
-    pub unsafe fn reset_fences(&self, fences: &[vk::Fence]) -> VkResult<()> {
+    pub unsafe fn transfer_submit<F: FnOnce(vk::CommandBuffer)>(
+        &self,
+        record: F,
+    ) -> track::Result<()> {
+        self.transfer_submit.submit(
+            &self.device_handle.device,
+            self.device_handle.queues.transfer.queue,
+            record,
+        )
+    }
+
+    #[inline(always)]
+    pub unsafe fn wait_for_fences(&self, fences: &[vk::Fence]) -> VkResult<()> {
         self.device_handle
             .device
-            .wait_for_fences(fences, true, u64::MAX)?;
+            .wait_for_fences(fences, true, u64::MAX)
+    }
 
+    #[inline(always)]
+    pub unsafe fn reset_fences(&self, fences: &[vk::Fence]) -> VkResult<()> {
         self.device_handle.device.reset_fences(fences)
     }
 
     #[inline(always)]
-    pub unsafe fn reset_commmand_buffers(&self) -> VkResult<()> {
-        self.device_handle.device.reset_command_pool(
-            self.command.command_pool,
-            vk::CommandPoolResetFlags::empty(),
-        )
+    pub unsafe fn reset_commmand_buffers(&self, command_pool: vk::CommandPool) -> VkResult<()> {
+        self.device_handle
+            .device
+            .reset_command_pool(command_pool, vk::CommandPoolResetFlags::empty())
     }
 
+    /// Returns the image to render into and whether the swapchain no longer matches the surface
+    /// exactly, it can still be presented to then.
+    ///
+    /// For an offscreen target `semaphore` and `fence` are left untouched, there is nothing to wait on.
     #[inline(always)]
     pub unsafe fn get_image(
         &self,
         semaphore: vk::Semaphore,
         fence: vk::Fence,
-    ) -> VkResult<(usize, vk::Image, vk::ImageView)> {
-        let next_image_index = self
-            .swapchain_handle
-            .swapchain_loader
-            .acquire_next_image(self.swapchain_handle.swapchain, u64::MAX, semaphore, fence)?
-            .0 as usize;
+    ) -> VkResult<(usize, vk::Image, vk::ImageView, bool)> {
+        match &self.render_target {
+            RenderTarget::Swapchain(swapchain_handle) => {
+                let (next_image_index, is_suboptimal) = swapchain_handle
+                    .swapchain_loader
+                    .acquire_next_image(swapchain_handle.swapchain, u64::MAX, semaphore, fence)?;
+                let next_image_index = next_image_index as usize;
 
-        Ok((
-            next_image_index,
-            self.swapchain_handle.images[next_image_index],
-            self.swapchain_handle.image_views[next_image_index],
-        ))
+                Ok((
+                    next_image_index,
+                    swapchain_handle.images[next_image_index],
+                    swapchain_handle.image_views[next_image_index],
+                    is_suboptimal,
+                ))
+            }
+            RenderTarget::Offscreen(offscreen_target) => Ok((
+                Default::default(),
+                offscreen_target.image,
+                offscreen_target.image_view,
+                false,
+            )),
+        }
     }
 
     #[inline(always)]
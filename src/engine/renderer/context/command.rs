@@ -11,7 +11,7 @@ impl Command {
     pub fn new(
         device: &ash::Device,
         queue_family_index: u32,
-        image_count: u32,
+        command_buffer_count: u32,
     ) -> track::Result<Self> {
         let command_pool_info = vk::CommandPoolCreateInfo::default()
             .queue_family_index(queue_family_index)
@@ -26,7 +26,7 @@ impl Command {
         let command_buffer_info = vk::CommandBufferAllocateInfo::default()
             .command_pool(command_pool)
             .level(vk::CommandBufferLevel::PRIMARY)
-            .command_buffer_count(image_count);
+            .command_buffer_count(command_buffer_count);
 
         let command_buffers = unsafe {
             device
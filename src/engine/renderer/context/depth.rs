@@ -33,7 +33,7 @@ impl DepthBuffer {
         };
 
         let image = resources
-            .allocate_depth_image(&image_info, &allocation_info)
+            .allocate_image(&image_info, &allocation_info)
             .track()?;
 
         let image_view_info = vk::ImageViewCreateInfo::default()
@@ -51,4 +51,10 @@ impl DepthBuffer {
 
         Ok(Self { image, image_view })
     }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device, resources: &mut super::resources::Resources) {
+        unsafe { device.destroy_image_view(self.image_view, None) };
+        resources.destroy_image(self.image);
+    }
 }
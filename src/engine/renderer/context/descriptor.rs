@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use ash::vk;
+use smallvec::SmallVec;
+use track::Context;
+
+type BindingKey = (u32, i32, u32, u32);
+
+/// Creates every distinct descriptor set layout once and hands out the cached one afterwards.
+#[derive(Default)]
+pub struct DescriptorLayoutCache {
+    layouts: HashMap<SmallVec<[BindingKey; 4]>, vk::DescriptorSetLayout>,
+}
+
+impl DescriptorLayoutCache {
+    pub fn get_or_create(
+        &mut self,
+        device: &ash::Device,
+        bindings: &[vk::DescriptorSetLayoutBinding],
+    ) -> track::Result<vk::DescriptorSetLayout> {
+        let mut key: SmallVec<[BindingKey; 4]> = bindings
+            .iter()
+            .map(|binding| {
+                (
+                    binding.binding,
+                    binding.descriptor_type.as_raw(),
+                    binding.descriptor_count,
+                    binding.stage_flags.as_raw(),
+                )
+            })
+            .collect();
+        key.sort_unstable();
+
+        if let Some(&layout) = self.layouts.get(&key) {
+            return Ok(layout);
+        }
+
+        let layout_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(bindings);
+        let layout = unsafe {
+            device
+                .create_descriptor_set_layout(&layout_info, None)
+                .track()?
+        };
+
+        self.layouts.insert(key, layout);
+
+        Ok(layout)
+    }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        self.layouts.values().for_each(|&layout| unsafe {
+            device.destroy_descriptor_set_layout(layout, None);
+        });
+    }
+}
+
+/// Allocates descriptor sets from a growing list of pools.
+///
+/// Meant to be owned by a single frame in flight and reset once the frame's fence is signaled.
+#[derive(Default)]
+pub struct DescriptorAllocator {
+    current_pool: Option<vk::DescriptorPool>,
+    used_pools: Vec<vk::DescriptorPool>,
+    free_pools: Vec<vk::DescriptorPool>,
+}
+
+impl DescriptorAllocator {
+    pub const SETS_PER_POOL: u32 = 256;
+    const POOL_SIZE_RATIOS: [(vk::DescriptorType, f32); 4] = [
+        (vk::DescriptorType::UNIFORM_BUFFER, 2.0),
+        (vk::DescriptorType::STORAGE_BUFFER, 2.0),
+        (vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 4.0),
+        (vk::DescriptorType::STORAGE_IMAGE, 1.0),
+    ];
+
+    pub fn allocate(
+        &mut self,
+        device: &ash::Device,
+        layout: vk::DescriptorSetLayout,
+    ) -> track::Result<vk::DescriptorSet> {
+        let layouts = [layout];
+
+        let pool = match self.current_pool {
+            Some(pool) => pool,
+            None => self.grab_pool(device).track()?,
+        };
+
+        let allocate_info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(pool)
+            .set_layouts(&layouts);
+
+        match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+            Ok(descriptor_sets) => Ok(descriptor_sets[0]),
+            Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY | vk::Result::ERROR_FRAGMENTED_POOL) => {
+                let pool = self.grab_pool(device).track()?;
+                let allocate_info = allocate_info.descriptor_pool(pool);
+
+                Ok(unsafe { device.allocate_descriptor_sets(&allocate_info).track()?[0] })
+            }
+            Err(error) => Err(error).track(),
+        }
+    }
+
+    /// Returns every allocated set back into the pools.
+    pub fn reset(&mut self, device: &ash::Device) -> track::Result<()> {
+        for &pool in self.used_pools.iter() {
+            unsafe {
+                device
+                    .reset_descriptor_pool(pool, vk::DescriptorPoolResetFlags::empty())
+                    .track()?
+            };
+        }
+
+        self.free_pools.append(&mut self.used_pools);
+        self.current_pool = None;
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        self.used_pools
+            .iter()
+            .chain(self.free_pools.iter())
+            .for_each(|&pool| unsafe { device.destroy_descriptor_pool(pool, None) });
+    }
+
+    fn grab_pool(&mut self, device: &ash::Device) -> track::Result<vk::DescriptorPool> {
+        let pool = match self.free_pools.pop() {
+            Some(pool) => pool,
+            None => {
+                let pool_sizes = Self::POOL_SIZE_RATIOS.map(|(ty, ratio)| vk::DescriptorPoolSize {
+                    ty,
+                    descriptor_count: (ratio * Self::SETS_PER_POOL as f32) as u32,
+                });
+
+                let pool_info = vk::DescriptorPoolCreateInfo::default()
+                    .max_sets(Self::SETS_PER_POOL)
+                    .pool_sizes(&pool_sizes);
+
+                unsafe { device.create_descriptor_pool(&pool_info, None).track()? }
+            }
+        };
+
+        self.used_pools.push(pool);
+        self.current_pool = Some(pool);
+
+        Ok(pool)
+    }
+}
+
+enum DescriptorInfo {
+    Buffer(usize),
+    Image(usize),
+}
+
+/// Collects descriptor writes and applies them to a set in one `vkUpdateDescriptorSets` call.
+#[derive(Default)]
+pub struct DescriptorWriter {
+    buffer_infos: SmallVec<[vk::DescriptorBufferInfo; 4]>,
+    image_infos: SmallVec<[vk::DescriptorImageInfo; 4]>,
+    writes: SmallVec<[(u32, vk::DescriptorType, DescriptorInfo); 8]>,
+}
+
+impl DescriptorWriter {
+    /// Writes a uniform or storage buffer, dynamic variants included.
+    pub fn write_buffer(
+        mut self,
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+        buffer: vk::Buffer,
+        offset: u64,
+        range: u64,
+    ) -> Self {
+        self.buffer_infos.push(vk::DescriptorBufferInfo {
+            buffer,
+            offset,
+            range,
+        });
+        self.writes.push((
+            binding,
+            descriptor_type,
+            DescriptorInfo::Buffer(self.buffer_infos.len() - 1),
+        ));
+
+        self
+    }
+
+    /// Writes a combined image sampler, sampled or storage image.
+    pub fn write_image(
+        mut self,
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+        image_view: vk::ImageView,
+        sampler: vk::Sampler,
+        image_layout: vk::ImageLayout,
+    ) -> Self {
+        self.image_infos.push(vk::DescriptorImageInfo {
+            sampler,
+            image_view,
+            image_layout,
+        });
+        self.writes.push((
+            binding,
+            descriptor_type,
+            DescriptorInfo::Image(self.image_infos.len() - 1),
+        ));
+
+        self
+    }
+
+    pub fn update(&self, device: &ash::Device, descriptor_set: vk::DescriptorSet) {
+        let writes: SmallVec<[vk::WriteDescriptorSet; 8]> = self
+            .writes
+            .iter()
+            .map(|(binding, descriptor_type, info)| {
+                let write = vk::WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(*binding)
+                    .descriptor_type(*descriptor_type);
+
+                match *info {
+                    DescriptorInfo::Buffer(index) => {
+                        write.buffer_info(std::slice::from_ref(&self.buffer_infos[index]))
+                    }
+                    DescriptorInfo::Image(index) => {
+                        write.image_info(std::slice::from_ref(&self.image_infos[index]))
+                    }
+                }
+            })
+            .collect();
+
+        unsafe { device.update_descriptor_sets(&writes, &[]) };
+    }
+}
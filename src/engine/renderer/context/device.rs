@@ -1,90 +1,90 @@
+use std::os::raw::c_char;
+
 use ash::vk;
+use smallvec::SmallVec;
 use tracing::info;
 use tracing_unwrap::ResultExt;
 use track::Context;
 
 use crate::engine::renderer::context::debug;
 
+use super::queue::{QueueFamilies, QueueManager};
+
 pub struct DeviceHandle {
     pub physical_device: vk::PhysicalDevice,
     pub device: ash::Device,
     pub device_properties: vk::PhysicalDeviceProperties,
-    pub queue_family_index: u32,
-    pub queue_graphics: vk::Queue, // TODO: Make a "Queue Manager" for the queues and store them not explicitly in `DeviceHandle`.
+    pub queues: QueueManager,
     pub surface_capabilities: vk::SurfaceCapabilitiesKHR,
     pub surface_format: vk::SurfaceFormatKHR,
     pub present_mode: vk::PresentModeKHR,
 }
 
 impl DeviceHandle {
+    /// Format of the color attachment used when there is no surface to query formats from.
+    pub const OFFSCREEN_SURFACE_FORMAT: vk::SurfaceFormatKHR = vk::SurfaceFormatKHR {
+        format: vk::Format::R8G8B8A8_SRGB,
+        color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+    };
+
     pub fn new(
         instance: &ash::Instance,
-        surface_handle: &super::surface::SurfaceHandle,
+        surface_handle: Option<&super::surface::SurfaceHandle>,
     ) -> track::Result<Self> {
         info!("Choosing compitable GPU");
 
-        let (physical_device, device_properties, queue_family_index, surface_format, present_mode) = unsafe {
+        let (physical_device, device_properties, queue_families, surface_format, present_mode) = unsafe {
             instance
                 .enumerate_physical_devices()
                 .track()?
                 .iter()
                 .filter_map(|&physical_device| {
-                    let queue_family_index = match instance
-                        .get_physical_device_queue_family_properties(physical_device)
-                        .into_iter()
-                        .enumerate()
-                        .position(|(queue_family_index, queue_family_property)| {
-                            queue_family_property
-                                .queue_flags
-                                .contains(vk::QueueFlags::GRAPHICS)
-                                && surface_handle
-                                    .surface_loader
-                                    .get_physical_device_surface_support(
-                                        physical_device,
-                                        queue_family_index as u32,
-                                        surface_handle.surface,
-                                    )
-                                    .unwrap_or_log()
-                        }) {
-                        Some(queue_family_index) => queue_family_index,
-                        None => return None,
-                    };
-
-                    let formats = surface_handle
-                        .surface_loader
-                        .get_physical_device_surface_formats(
-                            physical_device,
-                            surface_handle.surface,
-                        )
-                        .unwrap_or_log();
-
-                    let format = match formats.into_iter().find(|surface_format| {
-                        (surface_format.format == vk::Format::R8G8B8A8_SRGB
-                            || surface_format.format == vk::Format::B8G8R8A8_SRGB)
-                            && surface_format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
-                    }) {
-                        Some(surface_format) => surface_format,
-                        None => return None,
+                    let queue_families =
+                        QueueFamilies::find(instance, physical_device, surface_handle)?;
+
+                    let (format, present_mode) = match surface_handle {
+                        Some(surface_handle) => {
+                            let formats = surface_handle
+                                .surface_loader
+                                .get_physical_device_surface_formats(
+                                    physical_device,
+                                    surface_handle.surface,
+                                )
+                                .unwrap_or_log();
+
+                            let format = match formats.into_iter().find(|surface_format| {
+                                (surface_format.format == vk::Format::R8G8B8A8_SRGB
+                                    || surface_format.format == vk::Format::B8G8R8A8_SRGB)
+                                    && surface_format.color_space
+                                        == vk::ColorSpaceKHR::SRGB_NONLINEAR
+                            }) {
+                                Some(surface_format) => surface_format,
+                                None => return None,
+                            };
+
+                            let present_mode = surface_handle
+                                .surface_loader
+                                .get_physical_device_surface_present_modes(
+                                    physical_device,
+                                    surface_handle.surface,
+                                )
+                                .unwrap_or_log()
+                                .into_iter()
+                                .find(|&present_mode| present_mode == vk::PresentModeKHR::MAILBOX)
+                                .unwrap_or(vk::PresentModeKHR::FIFO);
+
+                            (format, present_mode)
+                        }
+                        None => (Self::OFFSCREEN_SURFACE_FORMAT, vk::PresentModeKHR::FIFO),
                     };
 
-                    let present_mode = surface_handle
-                        .surface_loader
-                        .get_physical_device_surface_present_modes(
-                            physical_device,
-                            surface_handle.surface,
-                        )
-                        .unwrap_or_log()
-                        .into_iter()
-                        .find(|&present_mode| present_mode == vk::PresentModeKHR::MAILBOX)
-                        .unwrap_or(vk::PresentModeKHR::FIFO);
-
                     let device_properties =
                         instance.get_physical_device_properties(physical_device);
 
                     Some((
                         physical_device,
                         device_properties,
-                        queue_family_index as u32,
+                        queue_families,
                         format,
                         present_mode,
                     ))
@@ -107,14 +107,22 @@ impl DeviceHandle {
         };
         info!("Found compitable GPU: {device_name}");
 
-        let surface_capabilities = unsafe {
-            surface_handle
-                .surface_loader
-                .get_physical_device_surface_capabilities(physical_device, surface_handle.surface)
-                .unwrap_or_log()
-        };
-
-        let device_extension_names = [ash::extensions::khr::Swapchain::name().as_ptr()];
+        let surface_capabilities = surface_handle
+            .map(|surface_handle| unsafe {
+                surface_handle
+                    .surface_loader
+                    .get_physical_device_surface_capabilities(
+                        physical_device,
+                        surface_handle.surface,
+                    )
+                    .unwrap_or_log()
+            })
+            .unwrap_or_default();
+
+        let device_extension_names: SmallVec<[*const c_char; 1]> = surface_handle
+            .map(|_| ash::extensions::khr::Swapchain::name().as_ptr())
+            .into_iter()
+            .collect();
         let device_layer_names = [
             #[cfg(feature = "validation")]
             debug::VALIDATION_LAYER_EXTENSION_NAME.as_ptr(),
@@ -123,9 +131,7 @@ impl DeviceHandle {
             .dynamic_rendering(true)
             .synchronization2(true);
 
-        let queue_create_info = [vk::DeviceQueueCreateInfo::default()
-            .queue_family_index(queue_family_index)
-            .queue_priorities(&[1.0])];
+        let queue_create_info = QueueManager::queue_create_infos(&queue_families);
 
         let device_info = vk::DeviceCreateInfo::default()
             .enabled_extension_names(&device_extension_names)
@@ -138,17 +144,35 @@ impl DeviceHandle {
                 .track()?
         };
 
-        let queue_graphics = unsafe { device.get_device_queue(queue_family_index, 0) };
+        let queues = QueueManager::new(&device, queue_families);
 
         Ok(Self {
             physical_device,
             device,
             device_properties,
-            queue_family_index,
-            queue_graphics,
+            queues,
             surface_capabilities,
             surface_format,
             present_mode,
         })
     }
+
+    /// Re-queries the surface capabilities, their current extent changes together with the window.
+    #[inline]
+    pub fn update_surface_capabilities(
+        &mut self,
+        surface_handle: &super::surface::SurfaceHandle,
+    ) -> track::Result<()> {
+        self.surface_capabilities = unsafe {
+            surface_handle
+                .surface_loader
+                .get_physical_device_surface_capabilities(
+                    self.physical_device,
+                    surface_handle.surface,
+                )
+                .track()?
+        };
+
+        Ok(())
+    }
 }
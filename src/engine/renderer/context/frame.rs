@@ -0,0 +1,65 @@
+use ash::vk;
+use track::Context;
+
+use super::{command::Command, descriptor::DescriptorAllocator, pipeline::SceneUniforms};
+
+pub const MAX_FRAMES_IN_FLIGHT: usize = 3;
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+/// Resources owned by a single frame in flight, so the CPU can record a frame
+/// while the GPU is still executing the previous ones.
+pub struct Frame {
+    pub command: Command,
+    pub render_fence: vk::Fence,
+    pub render_semaphore: vk::Semaphore,
+    pub present_semaphore: vk::Semaphore,
+    pub descriptor_allocator: DescriptorAllocator,
+    pub scene_buffer: vk::Buffer,
+}
+
+impl Frame {
+    pub fn new(
+        device: &ash::Device,
+        resources: &mut super::resources::Resources,
+        queue_family_index: u32,
+    ) -> track::Result<Self> {
+        let command = Command::new(device, queue_family_index, 1).track()?;
+
+        let fence_info = vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED);
+        let render_fence = unsafe { device.create_fence(&fence_info, None).track()? };
+
+        let semaphore_info = vk::SemaphoreCreateInfo::default();
+        let render_semaphore = unsafe { device.create_semaphore(&semaphore_info, None).track()? };
+        let present_semaphore = unsafe { device.create_semaphore(&semaphore_info, None).track()? };
+
+        let scene_buffer = resources
+            .allocate_host_buffer(SceneUniforms::SIZE, vk::BufferUsageFlags::UNIFORM_BUFFER)
+            .track()?;
+
+        Ok(Self {
+            command,
+            render_fence,
+            render_semaphore,
+            present_semaphore,
+            descriptor_allocator: Default::default(),
+            scene_buffer,
+        })
+    }
+
+    #[inline(always)]
+    pub fn command_buffer(&self) -> vk::CommandBuffer {
+        self.command.command_buffers[0]
+    }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_command_pool(self.command.command_pool, None);
+            device.destroy_fence(self.render_fence, None);
+            device.destroy_semaphore(self.present_semaphore, None);
+            device.destroy_semaphore(self.render_semaphore, None);
+        }
+
+        self.descriptor_allocator.destroy(device);
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use smallvec::SmallVec;
+use tracing::{error, info, warn};
+use track::Context;
+
+use crate::engine::utils::{
+    paths,
+    shader_compiler::{is_shader_stage, ShaderCompiler},
+};
+
+/// Watches the GLSL shaders and recompiles them into the SPIR-V directory when they change.
+///
+/// A changed include recompiles every shader, as the watcher doesn't know which ones include it.
+pub struct ShaderWatcher {
+    // NOTE: Watching stops once the watcher is dropped.
+    _watcher: notify::RecommendedWatcher,
+    receiver: Receiver<notify::Result<notify::Event>>,
+    compiler: ShaderCompiler,
+}
+
+impl ShaderWatcher {
+    pub fn new() -> track::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+
+        let mut watcher = notify::recommended_watcher(sender).track()?;
+        watcher
+            .watch(&paths::shader_sources_dir(), RecursiveMode::NonRecursive)
+            .track()?;
+
+        Ok(Self {
+            _watcher: watcher,
+            receiver,
+            compiler: ShaderCompiler::new(paths::shader_sources_dir()),
+        })
+    }
+
+    /// Recompiles the shaders changed since the last call and returns the names of those whose every
+    /// changed stage compiled, e.g. `mesh` for `mesh.frag`. Compilation errors are logged.
+    pub fn recompile_changed(&self) -> SmallVec<[String; 2]> {
+        let mut changed_paths = HashSet::new();
+        while let Ok(event) = self.receiver.try_recv() {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    changed_paths.extend(event.paths)
+                }
+                Ok(_) => (),
+                Err(error) => warn!("Failed to watch the shaders: {error}"),
+            }
+        }
+
+        if changed_paths.is_empty() {
+            return SmallVec::new();
+        }
+
+        let sources: Vec<PathBuf> = match changed_paths.iter().all(|path| is_shader_stage(path)) {
+            true => changed_paths.into_iter().collect(),
+            false => std::fs::read_dir(paths::shader_sources_dir())
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| is_shader_stage(path))
+                .collect(),
+        };
+
+        let mut compiled_names = SmallVec::<[String; 2]>::new();
+        let mut failed_names = SmallVec::<[String; 2]>::new();
+        sources.iter().for_each(|source| {
+            let name = shader_name(source);
+            let file_name = source.file_name().unwrap_or_default().to_string_lossy();
+            let output = paths::shaders_dir().join(format!("{file_name}.spv"));
+
+            match self.compiler.compile(source, &output, None) {
+                Ok(()) => {
+                    info!("Recompiled {source:?}");
+                    compiled_names.push(name);
+                }
+                Err(error) => {
+                    error!("Failed to recompile {source:?}, keeping the old pipeline: {error}");
+                    failed_names.push(name);
+                }
+            }
+        });
+
+        compiled_names.sort();
+        compiled_names.dedup();
+        compiled_names.retain(|name| !failed_names.contains(name));
+
+        compiled_names
+    }
+}
+
+#[inline]
+fn shader_name(path: &Path) -> String {
+    path.file_name()
+        .map(|file_name| file_name.to_string_lossy())
+        .unwrap_or_default()
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .to_owned()
+}
@@ -0,0 +1,69 @@
+use ash::vk;
+use track::Context;
+
+use super::command::Command;
+
+/// One-shot command buffer for work the CPU waits on right away, e.g. resource uploads.
+pub struct ImmediateSubmit {
+    pub command: Command,
+    pub fence: vk::Fence,
+}
+
+impl ImmediateSubmit {
+    pub fn new(device: &ash::Device, queue_family_index: u32) -> track::Result<Self> {
+        let command = Command::new(device, queue_family_index, 1).track()?;
+
+        let fence_info = vk::FenceCreateInfo::default();
+        let fence = unsafe { device.create_fence(&fence_info, None).track()? };
+
+        Ok(Self { command, fence })
+    }
+
+    /// Records commands with `record`, submits them to `queue` and blocks until they finished.
+    pub unsafe fn submit<F: FnOnce(vk::CommandBuffer)>(
+        &self,
+        device: &ash::Device,
+        queue: vk::Queue,
+        record: F,
+    ) -> track::Result<()> {
+        let command_buffer = self.command.command_buffers[0];
+
+        device
+            .reset_command_pool(
+                self.command.command_pool,
+                vk::CommandPoolResetFlags::empty(),
+            )
+            .track()?;
+
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo::default()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        device
+            .begin_command_buffer(command_buffer, &command_buffer_begin_info)
+            .track()?;
+
+        record(command_buffer);
+
+        device.end_command_buffer(command_buffer).track()?;
+
+        let command_buffers = [command_buffer];
+        let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
+
+        device
+            .queue_submit(queue, &[submit_info], self.fence)
+            .track()?;
+        device
+            .wait_for_fences(&[self.fence], true, u64::MAX)
+            .track()?;
+        device.reset_fences(&[self.fence]).track()?;
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_command_pool(self.command.command_pool, None);
+            device.destroy_fence(self.fence, None);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::ffi::CStr;
+use std::os::raw::c_char;
 
 use ash::vk;
 use raw_window_handle::HasRawDisplayHandle;
@@ -18,7 +19,7 @@ impl InstaceHandle {
     const APPLICATION_NAME: &CStr = cstr!("Triangle");
     const APPLICATION_VERSION: u32 = vk::make_api_version(0, 1, 0, 0);
 
-    pub fn new(window: &winit::window::Window) -> track::Result<Self> {
+    pub fn new(window: Option<&winit::window::Window>) -> track::Result<Self> {
         let entry = unsafe { ash::Entry::load().track()? };
 
         info!("Setting up application Info");
@@ -33,8 +34,13 @@ impl InstaceHandle {
         info!("Setting up Vulkan Instance Info");
 
         let instance_extensions = {
-            let surface_extensions =
-                ash_window::enumerate_required_extensions(window.raw_display_handle()).track()?;
+            let surface_extensions: &[*const c_char] = match window {
+                Some(window) => {
+                    ash_window::enumerate_required_extensions(window.raw_display_handle())
+                        .track()?
+                }
+                None => &[],
+            };
             let debug_utils_ext = vec![ash::extensions::ext::DebugUtils::name().as_ptr()];
 
             [debug_utils_ext, (*surface_extensions).to_owned()].concat()
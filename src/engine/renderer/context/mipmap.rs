@@ -0,0 +1,416 @@
+use ash::vk;
+use tracing::info;
+use track::Context;
+
+use super::{
+    descriptor::{DescriptorAllocator, DescriptorLayoutCache, DescriptorWriter},
+    pipeline::ComputePipelineHandle,
+    resources::{StagedImage, TextureImage},
+};
+
+/// Generates the mip chain of freshly staged textures, must be recorded on the graphics queue.
+///
+/// Levels are blitted from each other with a linear filter when the texture format supports it,
+/// otherwise they are downsampled by a compute shader.
+///
+/// Expects every level in `TRANSFER_DST_OPTIMAL` layout with the first one already written,
+/// leaves every level in `SHADER_READ_ONLY_OPTIMAL` layout.
+pub struct MipmapGenerator {
+    downsampler: Option<ComputeDownsampler>,
+}
+
+impl MipmapGenerator {
+    pub fn new(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &ash::Device,
+        pipeline_cache: vk::PipelineCache,
+        descriptor_layout_cache: &mut DescriptorLayoutCache,
+    ) -> track::Result<Self> {
+        let format_properties = unsafe {
+            instance.get_physical_device_format_properties(physical_device, TextureImage::FORMAT)
+        };
+
+        let supports_linear_blit = format_properties.optimal_tiling_features.contains(
+            vk::FormatFeatureFlags::BLIT_SRC
+                | vk::FormatFeatureFlags::BLIT_DST
+                | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR,
+        );
+
+        let downsampler = if supports_linear_blit {
+            info!("Generating mipmaps with linear blits");
+
+            None
+        } else {
+            info!("Generating mipmaps with the compute downsampler");
+
+            Some(ComputeDownsampler::new(device, pipeline_cache, descriptor_layout_cache).track()?)
+        };
+
+        Ok(Self { downsampler })
+    }
+
+    /// Usage and flags the texture images must be created with.
+    #[inline(always)]
+    pub fn image_requirements(&self) -> (vk::ImageUsageFlags, vk::ImageCreateFlags) {
+        match self.downsampler {
+            None => (
+                vk::ImageUsageFlags::TRANSFER_SRC,
+                vk::ImageCreateFlags::empty(),
+            ),
+            Some(_) => (
+                vk::ImageUsageFlags::STORAGE,
+                vk::ImageCreateFlags::MUTABLE_FORMAT | vk::ImageCreateFlags::EXTENDED_USAGE,
+            ),
+        }
+    }
+
+    /// Creates whatever per-level objects the images need, must be called before [`MipmapGenerator::record`]
+    /// with the same images.
+    pub fn prepare(
+        &mut self,
+        device: &ash::Device,
+        staged_images: impl Iterator<Item = StagedImage>,
+    ) -> track::Result<()> {
+        match &mut self.downsampler {
+            Some(downsampler) => downsampler.prepare(device, staged_images).track(),
+            None => Ok(()),
+        }
+    }
+
+    pub unsafe fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        staged_images: impl Iterator<Item = StagedImage>,
+    ) {
+        match &self.downsampler {
+            Some(downsampler) => downsampler.record(device, command_buffer, staged_images),
+            None => staged_images
+                .for_each(|staged_image| Self::record_blits(device, command_buffer, staged_image)),
+        }
+    }
+
+    /// Frees the per-level objects once the recorded commands have finished executing.
+    pub fn finish(&mut self, device: &ash::Device) -> track::Result<()> {
+        match &mut self.downsampler {
+            Some(downsampler) => downsampler.finish(device).track(),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        if let Some(downsampler) = &self.downsampler {
+            downsampler.destroy(device);
+        }
+    }
+
+    unsafe fn record_blits(
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        staged_image: StagedImage,
+    ) {
+        let mut level_extent = staged_image.image_extent;
+
+        for mip_level in 1..staged_image.mip_levels {
+            let image_memory_barriers = [vk::ImageMemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COPY | vk::PipelineStageFlags2::BLIT)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::BLIT)
+                .dst_access_mask(vk::AccessFlags2::TRANSFER_READ)
+                .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(staged_image.image)
+                .subresource_range(level_range(mip_level - 1, 1))];
+
+            device.cmd_pipeline_barrier2(
+                command_buffer,
+                &vk::DependencyInfo::default().image_memory_barriers(&image_memory_barriers),
+            );
+
+            let next_level_extent = next_level_extent(level_extent);
+
+            let regions = [vk::ImageBlit2::default()
+                .src_subresource(level_layers(mip_level - 1))
+                .src_offsets([Default::default(), far_corner(level_extent)])
+                .dst_subresource(level_layers(mip_level))
+                .dst_offsets([Default::default(), far_corner(next_level_extent)])];
+
+            let blit_image_info = vk::BlitImageInfo2::default()
+                .src_image(staged_image.image)
+                .src_image_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .dst_image(staged_image.image)
+                .dst_image_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .regions(&regions)
+                .filter(vk::Filter::LINEAR);
+
+            device.cmd_blit_image2(command_buffer, &blit_image_info);
+
+            level_extent = next_level_extent;
+        }
+
+        let last_level = staged_image.mip_levels - 1;
+        let read_barrier = vk::ImageMemoryBarrier2::default()
+            .dst_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+            .dst_access_mask(vk::AccessFlags2::SHADER_SAMPLED_READ)
+            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(staged_image.image);
+
+        // NOTE: Every level but the last one has been a blit source.
+        let image_memory_barriers = [
+            read_barrier
+                .src_stage_mask(vk::PipelineStageFlags2::BLIT)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_READ)
+                .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .subresource_range(level_range(0, last_level)),
+            read_barrier
+                .src_stage_mask(vk::PipelineStageFlags2::COPY | vk::PipelineStageFlags2::BLIT)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .subresource_range(level_range(last_level, 1)),
+        ];
+
+        let image_memory_barriers = match last_level {
+            0 => &image_memory_barriers[1..],
+            _ => &image_memory_barriers[..],
+        };
+
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo::default().image_memory_barriers(image_memory_barriers),
+        );
+    }
+}
+
+/// Compute fallback writing every level from the previous one through storage image views.
+struct ComputeDownsampler {
+    pipeline_handle: ComputePipelineHandle,
+    descriptor_allocator: DescriptorAllocator,
+    level_views: Vec<vk::ImageView>,
+    level_sets: Vec<vk::DescriptorSet>,
+}
+
+impl ComputeDownsampler {
+    const SHADER_NAME: &str = "mipmap";
+    /// Storage images don't support sRGB formats, the shader encodes and decodes it itself.
+    const STORAGE_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+    const SRC_LEVEL_BINDING: u32 = 0;
+    const DST_LEVEL_BINDING: u32 = 1;
+
+    fn new(
+        device: &ash::Device,
+        pipeline_cache: vk::PipelineCache,
+        descriptor_layout_cache: &mut DescriptorLayoutCache,
+    ) -> track::Result<Self> {
+        let pipeline_handle = ComputePipelineHandle::new(
+            device,
+            pipeline_cache,
+            descriptor_layout_cache,
+            Self::SHADER_NAME,
+        )
+        .track()?;
+
+        Ok(Self {
+            pipeline_handle,
+            descriptor_allocator: Default::default(),
+            level_views: Default::default(),
+            level_sets: Default::default(),
+        })
+    }
+
+    fn prepare(
+        &mut self,
+        device: &ash::Device,
+        staged_images: impl Iterator<Item = StagedImage>,
+    ) -> track::Result<()> {
+        for staged_image in staged_images {
+            let first_view = self.level_views.len();
+
+            for mip_level in 0..staged_image.mip_levels {
+                let image_view_info = vk::ImageViewCreateInfo::default()
+                    .image(staged_image.image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(Self::STORAGE_FORMAT)
+                    .subresource_range(level_range(mip_level, 1));
+
+                self.level_views
+                    .push(unsafe { device.create_image_view(&image_view_info, None).track()? });
+            }
+
+            for mip_level in 1..staged_image.mip_levels as usize {
+                let level_set = self
+                    .descriptor_allocator
+                    .allocate(device, self.pipeline_handle.set_layouts[0])
+                    .track()?;
+
+                DescriptorWriter::default()
+                    .write_image(
+                        Self::SRC_LEVEL_BINDING,
+                        vk::DescriptorType::STORAGE_IMAGE,
+                        self.level_views[first_view + mip_level - 1],
+                        vk::Sampler::null(),
+                        vk::ImageLayout::GENERAL,
+                    )
+                    .write_image(
+                        Self::DST_LEVEL_BINDING,
+                        vk::DescriptorType::STORAGE_IMAGE,
+                        self.level_views[first_view + mip_level],
+                        vk::Sampler::null(),
+                        vk::ImageLayout::GENERAL,
+                    )
+                    .update(device, level_set);
+
+                self.level_sets.push(level_set);
+            }
+        }
+
+        Ok(())
+    }
+
+    unsafe fn record(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        staged_images: impl Iterator<Item = StagedImage>,
+    ) {
+        let mut level_sets = self.level_sets.iter();
+
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            self.pipeline_handle.pipeline,
+        );
+
+        for staged_image in staged_images {
+            let image_memory_barriers = [vk::ImageMemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COPY)
+                .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .dst_access_mask(
+                    vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                )
+                .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .new_layout(vk::ImageLayout::GENERAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(staged_image.image)
+                .subresource_range(staged_image.subresource_range())];
+
+            device.cmd_pipeline_barrier2(
+                command_buffer,
+                &vk::DependencyInfo::default().image_memory_barriers(&image_memory_barriers),
+            );
+
+            let mut level_extent = staged_image.image_extent;
+
+            for mip_level in 1..staged_image.mip_levels {
+                // NOTE: Each level is read by the dispatch writing the next one.
+                if mip_level > 1 {
+                    let memory_barriers = [vk::MemoryBarrier2::default()
+                        .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                        .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                        .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                        .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_READ)];
+
+                    device.cmd_pipeline_barrier2(
+                        command_buffer,
+                        &vk::DependencyInfo::default().memory_barriers(&memory_barriers),
+                    );
+                }
+
+                level_extent = next_level_extent(level_extent);
+
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::COMPUTE,
+                    self.pipeline_handle.pipeline_layout,
+                    Default::default(),
+                    &[*level_sets
+                        .next()
+                        .expect("Images must be prepared before recording")],
+                    &[],
+                );
+                let [group_count_x, group_count_y, group_count_z] = self
+                    .pipeline_handle
+                    .group_counts([level_extent.width, level_extent.height, 1]);
+                device.cmd_dispatch(command_buffer, group_count_x, group_count_y, group_count_z);
+            }
+
+            let image_memory_barriers = [vk::ImageMemoryBarrier2::default()
+                .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                .dst_access_mask(vk::AccessFlags2::SHADER_SAMPLED_READ)
+                .old_layout(vk::ImageLayout::GENERAL)
+                .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(staged_image.image)
+                .subresource_range(staged_image.subresource_range())];
+
+            device.cmd_pipeline_barrier2(
+                command_buffer,
+                &vk::DependencyInfo::default().image_memory_barriers(&image_memory_barriers),
+            );
+        }
+    }
+
+    fn finish(&mut self, device: &ash::Device) -> track::Result<()> {
+        self.level_views.drain(..).for_each(|image_view| unsafe {
+            device.destroy_image_view(image_view, None);
+        });
+        self.level_sets.clear();
+
+        self.descriptor_allocator.reset(device).track()
+    }
+
+    #[inline]
+    fn destroy(&self, device: &ash::Device) {
+        self.descriptor_allocator.destroy(device);
+        self.pipeline_handle.destroy(device);
+    }
+}
+
+#[inline(always)]
+fn level_range(base_mip_level: u32, level_count: u32) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level,
+        level_count,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+#[inline(always)]
+fn level_layers(mip_level: u32) -> vk::ImageSubresourceLayers {
+    vk::ImageSubresourceLayers {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        mip_level,
+        base_array_layer: 0,
+        layer_count: 1,
+    }
+}
+
+#[inline(always)]
+fn next_level_extent(level_extent: vk::Extent3D) -> vk::Extent3D {
+    vk::Extent3D {
+        width: (level_extent.width / 2).max(1),
+        height: (level_extent.height / 2).max(1),
+        depth: 1,
+    }
+}
+
+#[inline(always)]
+fn far_corner(level_extent: vk::Extent3D) -> vk::Offset3D {
+    vk::Offset3D {
+        x: level_extent.width as i32,
+        y: level_extent.height as i32,
+        z: 1,
+    }
+}
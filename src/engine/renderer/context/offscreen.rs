@@ -0,0 +1,157 @@
+use std::{fs::File, io::BufWriter, io::Write, path::Path};
+
+use ash::vk;
+use track::Context;
+
+use super::resources::Resources;
+
+/// Color image rendered into instead of a swapchain image when there is no window.
+pub struct OffscreenTarget {
+    pub image: vk::Image,
+    pub image_view: vk::ImageView,
+    pub readback_buffer: vk::Buffer,
+    pub image_extent: vk::Extent2D,
+}
+
+impl OffscreenTarget {
+    pub const BYTES_PER_PIXEL: u64 = 4;
+
+    pub fn new(
+        device: &ash::Device,
+        resources: &mut Resources,
+        format: vk::Format,
+        image_extent: vk::Extent2D,
+    ) -> track::Result<Self> {
+        let image_info = vk::ImageCreateInfo::default()
+            .format(format)
+            .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC)
+            .extent(vk::Extent3D {
+                width: image_extent.width,
+                height: image_extent.height,
+                depth: 1,
+            })
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .mip_levels(1)
+            .array_layers(1)
+            .image_type(vk::ImageType::TYPE_2D);
+
+        let allocation_info = vma::AllocationCreateInfo {
+            required_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            usage: vma::MemoryUsage::AUTO,
+            ..Default::default()
+        };
+
+        let image = resources
+            .allocate_image(&image_info, &allocation_info)
+            .track()?;
+
+        let image_view_info = vk::ImageViewCreateInfo::default()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(format)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                level_count: 1,
+                layer_count: 1,
+                ..Default::default()
+            });
+
+        let image_view = unsafe { device.create_image_view(&image_view_info, None).track()? };
+
+        let readback_buffer = resources
+            .allocate_readback_buffer(Self::size_in_bytes(image_extent))
+            .track()?;
+
+        Ok(Self {
+            image,
+            image_view,
+            readback_buffer,
+            image_extent,
+        })
+    }
+
+    #[inline(always)]
+    pub fn size_in_bytes(image_extent: vk::Extent2D) -> u64 {
+        image_extent.width as u64 * image_extent.height as u64 * Self::BYTES_PER_PIXEL
+    }
+
+    /// Records a copy of the whole color image into the readback buffer and makes it visible to the host.
+    ///
+    /// The image must be in `TRANSFER_SRC_OPTIMAL` layout.
+    #[inline]
+    pub unsafe fn record_readback(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        let regions = [vk::BufferImageCopy::default()
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .image_extent(vk::Extent3D {
+                width: self.image_extent.width,
+                height: self.image_extent.height,
+                depth: 1,
+            })];
+
+        device.cmd_copy_image_to_buffer(
+            command_buffer,
+            self.image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            self.readback_buffer,
+            &regions,
+        );
+
+        let buffer_memory_barriers = [vk::BufferMemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::COPY)
+            .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags2::HOST)
+            .dst_access_mask(vk::AccessFlags2::HOST_READ)
+            .buffer(self.readback_buffer)
+            .size(vk::WHOLE_SIZE)];
+
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo::default().buffer_memory_barriers(&buffer_memory_barriers),
+        );
+    }
+}
+
+/// Writes tightly packed RGBA8 pixels into a file.
+///
+/// The format is picked by the extension: `.ppm` writes a binary PPM, anything else a PNG.
+pub fn write_image(path: &Path, image_extent: vk::Extent2D, pixels: &[u8]) -> track::Result<()> {
+    let mut writer = BufWriter::new(File::create(path).track()?);
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("ppm") => {
+            write!(
+                writer,
+                "P6\n{} {}\n255\n",
+                image_extent.width, image_extent.height
+            )
+            .track()?;
+
+            let rgb: Vec<u8> = pixels
+                .chunks_exact(OffscreenTarget::BYTES_PER_PIXEL as usize)
+                .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+                .collect();
+            writer.write_all(&rgb).track()?;
+        }
+        _ => {
+            let mut encoder = png::Encoder::new(writer, image_extent.width, image_extent.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+
+            encoder
+                .write_header()
+                .track()?
+                .write_image_data(pixels)
+                .track()?;
+
+            return Ok(());
+        }
+    }
+
+    writer.flush().track()
+}
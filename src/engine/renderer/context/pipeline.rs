@@ -1,78 +1,330 @@
+use std::mem;
+
 use ash::vk;
+use math::{Mat4, Vec4};
 use smallvec::SmallVec;
 use tracing::info;
 use track::Context;
 
 use crate::engine::{asset_system::mesh::VertexDescription, renderer::context::depth};
 
-pub struct PipelineHandle {
-    pub pipeline: vk::Pipeline,
-    pub pipeline_layout: vk::PipelineLayout,
+use super::{descriptor::DescriptorLayoutCache, reflect::DescriptorBinding, shader::ShaderHandle};
+
+mod registry;
+
+pub use registry::{ComputePipelineId, PipelineId, PipelineRegistry};
+
+/// Per-draw data pushed to the mesh vertex shader.
+#[repr(C)]
+pub struct MeshPushConstants {
+    pub model: Mat4,
 }
 
-impl PipelineHandle {
-    pub fn new(
+/// Per-frame data read by the shaders from the uniform buffer at set 0, binding 0.
+#[repr(C)]
+pub struct SceneUniforms {
+    pub view: Mat4,
+    pub projection: Mat4,
+    pub view_projection: Mat4,
+    pub camera_position: Vec4,
+}
+
+impl SceneUniforms {
+    pub const SIZE: u64 = mem::size_of::<Self>() as u64;
+    pub const SET: u32 = 0;
+    pub const BINDING: u32 = 0;
+}
+
+/// Texture sampled by the textured mesh fragment shader from set 1, binding 0.
+pub struct TextureBindings;
+
+impl TextureBindings {
+    pub const SET: u32 = 1;
+    pub const BINDING: u32 = 0;
+}
+
+/// How the colors written by a pipeline are combined with the attachment's.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Opaque,
+    /// Blends by the source alpha, e.g. for transparent surfaces.
+    Alpha,
+    /// Adds the source to the destination, e.g. for particles and light accumulation.
+    Additive,
+}
+
+impl BlendMode {
+    fn attachment_state(self) -> vk::PipelineColorBlendAttachmentState {
+        let attachment_state = vk::PipelineColorBlendAttachmentState::default()
+            .color_write_mask(vk::ColorComponentFlags::RGBA);
+
+        match self {
+            Self::Opaque => attachment_state,
+            Self::Alpha => attachment_state
+                .blend_enable(true)
+                .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                .color_blend_op(vk::BlendOp::ADD)
+                .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                .alpha_blend_op(vk::BlendOp::ADD),
+            Self::Additive => attachment_state
+                .blend_enable(true)
+                .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                .dst_color_blend_factor(vk::BlendFactor::ONE)
+                .color_blend_op(vk::BlendOp::ADD)
+                .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                .dst_alpha_blend_factor(vk::BlendFactor::ONE)
+                .alpha_blend_op(vk::BlendOp::ADD),
+        }
+    }
+}
+
+/// Description of a graphics pipeline, kept by the [`PipelineRegistry`] to rebuild it.
+///
+/// Defaults to opaque triangle lists with front faces culled and the depth tested and written,
+/// rendering into the render target and the depth buffer.
+///
+/// Viewport and scissor are always dynamic, so pipelines don't depend on the render target's extent.
+#[derive(Clone)]
+pub struct PipelineBuilder {
+    shader_name: String,
+    vertex_description: VertexDescription,
+    topology: vk::PrimitiveTopology,
+    polygon_mode: vk::PolygonMode,
+    cull_mode: vk::CullModeFlags,
+    front_face: vk::FrontFace,
+    blend_mode: BlendMode,
+    depth_test: bool,
+    depth_write: bool,
+    depth_compare_op: vk::CompareOp,
+    /// Empty for the format of the render target.
+    color_formats: SmallVec<[vk::Format; 1]>,
+    depth_format: Option<vk::Format>,
+    /// Dynamic states besides the viewport and scissor.
+    dynamic_states: SmallVec<[vk::DynamicState; 4]>,
+}
+
+impl PipelineBuilder {
+    /// Vulkan 1.3 dynamic states a pipeline can opt into with [`PipelineBuilder::dynamic_state`].
+    pub const SUPPORTED_DYNAMIC_STATES: [vk::DynamicState; 6] = [
+        vk::DynamicState::PRIMITIVE_TOPOLOGY,
+        vk::DynamicState::CULL_MODE,
+        vk::DynamicState::FRONT_FACE,
+        vk::DynamicState::DEPTH_TEST_ENABLE,
+        vk::DynamicState::DEPTH_WRITE_ENABLE,
+        vk::DynamicState::DEPTH_COMPARE_OP,
+    ];
+
+    /// Starts a pipeline of every compiled stage of the shader, e.g. `mesh.vert` and `mesh.frag` for `mesh`.
+    pub fn new(shader_name: impl Into<String>) -> Self {
+        Self {
+            shader_name: shader_name.into(),
+            vertex_description: VertexDescription::new(),
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            polygon_mode: vk::PolygonMode::FILL,
+            cull_mode: vk::CullModeFlags::FRONT,
+            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+            blend_mode: BlendMode::default(),
+            depth_test: true,
+            depth_write: true,
+            depth_compare_op: vk::CompareOp::LESS_OR_EQUAL,
+            color_formats: SmallVec::new(),
+            depth_format: Some(depth::DepthBuffer::DEPTH_BUFFER_FORMAT),
+            dynamic_states: SmallVec::new(),
+        }
+    }
+
+    /// Layout of the vertex buffer, the vertex shader's inputs must be part of it.
+    #[inline(always)]
+    pub fn vertex_description(mut self, vertex_description: VertexDescription) -> Self {
+        self.vertex_description = vertex_description;
+        self
+    }
+
+    #[inline(always)]
+    pub fn topology(mut self, topology: vk::PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Modes other than `FILL` need the `fillModeNonSolid` device feature.
+    #[inline(always)]
+    pub fn polygon_mode(mut self, polygon_mode: vk::PolygonMode) -> Self {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    #[inline(always)]
+    pub fn cull_mode(mut self, cull_mode: vk::CullModeFlags) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    #[inline(always)]
+    pub fn front_face(mut self, front_face: vk::FrontFace) -> Self {
+        self.front_face = front_face;
+        self
+    }
+
+    #[inline(always)]
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    #[inline(always)]
+    pub fn depth_test(mut self, depth_test: bool) -> Self {
+        self.depth_test = depth_test;
+        self
+    }
+
+    #[inline(always)]
+    pub fn depth_write(mut self, depth_write: bool) -> Self {
+        self.depth_write = depth_write;
+        self
+    }
+
+    #[inline(always)]
+    pub fn depth_compare_op(mut self, depth_compare_op: vk::CompareOp) -> Self {
+        self.depth_compare_op = depth_compare_op;
+        self
+    }
+
+    /// Formats of the color attachments, every one is blended by the same [`BlendMode`].
+    #[inline(always)]
+    pub fn color_formats(mut self, color_formats: &[vk::Format]) -> Self {
+        self.color_formats = color_formats.into();
+        self
+    }
+
+    /// Format of the depth attachment, `None` for pipelines rendering without one.
+    #[inline(always)]
+    pub fn depth_format(mut self, depth_format: Option<vk::Format>) -> Self {
+        self.depth_format = depth_format;
+        self
+    }
+
+    /// Makes one of the [`PipelineBuilder::SUPPORTED_DYNAMIC_STATES`] dynamic, the value given to
+    /// the builder is then set whenever the pipeline is bound and can be changed between draws.
+    #[inline]
+    pub fn dynamic_state(mut self, dynamic_state: vk::DynamicState) -> Self {
+        assert!(
+            Self::SUPPORTED_DYNAMIC_STATES.contains(&dynamic_state),
+            "Unsupported dynamic state {dynamic_state:?}"
+        );
+
+        if !self.dynamic_states.contains(&dynamic_state) {
+            self.dynamic_states.push(dynamic_state);
+        }
+        self
+    }
+
+    #[inline(always)]
+    pub fn shader_name(&self) -> &str {
+        &self.shader_name
+    }
+
+    /// Creates the pipeline, its layout and vertex input are reflected from the shaders.
+    ///
+    /// `target_format` is the format of the render target, used without explicit color formats.
+    pub fn build(
+        &self,
         device: &ash::Device,
-        shader_handle: &super::shader::ShaderHandle,
-        format: vk::Format,
-        image_extent: vk::Extent2D,
-    ) -> track::Result<Self> {
-        info!("Preparing Graphics Pipeline");
+        pipeline_cache: vk::PipelineCache,
+        descriptor_layout_cache: &mut DescriptorLayoutCache,
+        target_format: vk::Format,
+    ) -> track::Result<PipelineHandle> {
+        let shader_handle = ShaderHandle::new(device, &self.shader_name).track()?;
+        let pipeline_handle = self.create_pipeline(
+            device,
+            &shader_handle,
+            pipeline_cache,
+            descriptor_layout_cache,
+            target_format,
+        );
 
-        let shader_stages = Self::create_shader_stages(&shader_handle.shader_modules);
+        shader_handle.destroy(device);
 
-        let assembly_info = vk::PipelineInputAssemblyStateCreateInfo::default()
-            .topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        pipeline_handle
+    }
+
+    fn create_pipeline(
+        &self,
+        device: &ash::Device,
+        shader_handle: &ShaderHandle,
+        pipeline_cache: vk::PipelineCache,
+        descriptor_layout_cache: &mut DescriptorLayoutCache,
+        target_format: vk::Format,
+    ) -> track::Result<PipelineHandle> {
+        info!("Preparing Graphics Pipeline `{}`", self.shader_name);
+
+        let shader_stages = shader_handle.stage_infos();
 
-        let viewports = [vk::Viewport::default()
-            .height(image_extent.height as f32)
-            .width(image_extent.width as f32)
-            .max_depth(1.0)];
-        let scissors = [vk::Rect2D {
-            offset: vk::Offset2D { x: 0, y: 0 },
-            extent: image_extent,
-        }];
+        let assembly_info =
+            vk::PipelineInputAssemblyStateCreateInfo::default().topology(self.topology);
 
         let viewport_state = vk::PipelineViewportStateCreateInfo::default()
-            .viewports(&viewports)
-            .scissors(&scissors);
+            .viewport_count(1)
+            .scissor_count(1);
 
-        let vertex_description = VertexDescription::new();
-        let bindings = [vertex_description.binding];
+        let dynamic_states: SmallVec<[_; 8]> =
+            [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR]
+                .into_iter()
+                .chain(self.dynamic_states.iter().copied())
+                .collect();
+        let dynamic_state_info =
+            vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+
+        let vertex_attributes = shader_handle
+            .reflection
+            .vertex_attributes(&self.vertex_description)
+            .track()?;
+        // NOTE: Shaders without vertex inputs, e.g. fullscreen passes, don't read the vertex buffer.
+        let bindings = match vertex_attributes.is_empty() {
+            true => SmallVec::<[_; 1]>::new(),
+            false => SmallVec::from_buf([self.vertex_description.binding]),
+        };
 
         let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::default()
             .vertex_binding_descriptions(&bindings)
-            .vertex_attribute_descriptions(&vertex_description.attributes);
+            .vertex_attribute_descriptions(&vertex_attributes);
 
         let rasterization_info = vk::PipelineRasterizationStateCreateInfo::default()
-            .cull_mode(vk::CullModeFlags::FRONT)
-            .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+            .cull_mode(self.cull_mode)
+            .front_face(self.front_face)
             .line_width(1.0)
-            .polygon_mode(vk::PolygonMode::FILL);
+            .polygon_mode(self.polygon_mode);
 
         let multisample_state = vk::PipelineMultisampleStateCreateInfo::default()
             .rasterization_samples(vk::SampleCountFlags::TYPE_1);
 
-        let color_blending_state = [vk::PipelineColorBlendAttachmentState::default()
-            .color_write_mask(vk::ColorComponentFlags::RGBA)];
+        let color_formats = match self.color_formats.is_empty() {
+            true => SmallVec::from_buf([target_format]),
+            false => self.color_formats.clone(),
+        };
+        let color_blending_state: SmallVec<[_; 1]> = color_formats
+            .iter()
+            .map(|_| self.blend_mode.attachment_state())
+            .collect();
 
         let color_blend_attachment =
             vk::PipelineColorBlendStateCreateInfo::default().attachments(&color_blending_state);
 
+        let has_depth = self.depth_format.is_some();
         let depth_stencil_state_info = vk::PipelineDepthStencilStateCreateInfo::default()
-            .depth_test_enable(true)
-            .depth_write_enable(true)
-            .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+            .depth_test_enable(has_depth && self.depth_test)
+            .depth_write_enable(has_depth && self.depth_write)
+            .depth_compare_op(self.depth_compare_op)
             .max_depth_bounds(1.0);
 
-        let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default();
-        let pipeline_layout =
-            unsafe { device.create_pipeline_layout(&pipeline_layout_info, None) }.track()?;
+        let (pipeline_layout, set_layouts, push_constant_stages) =
+            create_pipeline_layout(device, shader_handle, descriptor_layout_cache).track()?;
 
-        let color_attachment_infos = [format];
         let mut pipeline_rendering_info = vk::PipelineRenderingCreateInfo::default()
-            .color_attachment_formats(&color_attachment_infos)
-            .depth_attachment_format(depth::DepthBuffer::DEPTH_BUFFER_FORMAT);
+            .color_attachment_formats(&color_formats)
+            .depth_attachment_format(self.depth_format.unwrap_or(vk::Format::UNDEFINED));
 
         let pipeline_infos = [vk::GraphicsPipelineCreateInfo::default()
             .vertex_input_state(&vertex_input_state)
@@ -83,34 +335,242 @@ impl PipelineHandle {
             .rasterization_state(&rasterization_info)
             .multisample_state(&multisample_state)
             .color_blend_state(&color_blend_attachment)
+            .dynamic_state(&dynamic_state_info)
             .layout(pipeline_layout)
             .push_next(&mut pipeline_rendering_info)];
 
-        let pipeline = unsafe {
-            device
-                .create_graphics_pipelines(vk::PipelineCache::null(), &pipeline_infos, None)
-                .unwrap()
-                .remove(Default::default())
+        let pipeline =
+            unsafe { device.create_graphics_pipelines(pipeline_cache, &pipeline_infos, None) };
+
+        let pipeline = match pipeline {
+            Ok(mut pipelines) => pipelines.remove(Default::default()),
+            Err((_, error)) => {
+                unsafe { device.destroy_pipeline_layout(pipeline_layout, None) };
+
+                return Err(error).track();
+            }
         };
 
-        Ok(Self {
+        Ok(PipelineHandle {
             pipeline,
             pipeline_layout,
+            set_layouts,
+            push_constant_stages,
+            dynamic_state: DynamicState {
+                dynamic_states: self.dynamic_states.clone(),
+                topology: self.topology,
+                cull_mode: self.cull_mode,
+                front_face: self.front_face,
+                depth_test: has_depth && self.depth_test,
+                depth_write: has_depth && self.depth_write,
+                depth_compare_op: self.depth_compare_op,
+            },
         })
     }
+}
 
-    #[inline]
-    fn create_shader_stages<'a>(
-        shader_modules: &SmallVec<[(vk::ShaderModule, vk::ShaderStageFlags); 2]>,
-    ) -> SmallVec<[vk::PipelineShaderStageCreateInfo<'a>; 2]> {
-        shader_modules
+/// Values of the optional dynamic states the pipeline was built with.
+pub struct DynamicState {
+    dynamic_states: SmallVec<[vk::DynamicState; 4]>,
+    topology: vk::PrimitiveTopology,
+    cull_mode: vk::CullModeFlags,
+    front_face: vk::FrontFace,
+    depth_test: bool,
+    depth_write: bool,
+    depth_compare_op: vk::CompareOp,
+}
+
+impl DynamicState {
+    /// Sets the pipeline's values of its dynamic states, must be recorded after binding it.
+    ///
+    /// Viewport and scissor are left alone, they belong to the render target rather than the pipeline.
+    pub unsafe fn record(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        self.dynamic_states
             .iter()
-            .map(|(shader_module, shader_stage)| {
-                vk::PipelineShaderStageCreateInfo::default()
-                    .name(super::shader::SHADER_ENTRY_NAME)
-                    .stage(*shader_stage)
-                    .module(*shader_module)
-            })
-            .collect()
+            .for_each(|&dynamic_state| match dynamic_state {
+                vk::DynamicState::PRIMITIVE_TOPOLOGY => {
+                    device.cmd_set_primitive_topology(command_buffer, self.topology)
+                }
+                vk::DynamicState::CULL_MODE => {
+                    device.cmd_set_cull_mode(command_buffer, self.cull_mode)
+                }
+                vk::DynamicState::FRONT_FACE => {
+                    device.cmd_set_front_face(command_buffer, self.front_face)
+                }
+                vk::DynamicState::DEPTH_TEST_ENABLE => {
+                    device.cmd_set_depth_test_enable(command_buffer, self.depth_test)
+                }
+                vk::DynamicState::DEPTH_WRITE_ENABLE => {
+                    device.cmd_set_depth_write_enable(command_buffer, self.depth_write)
+                }
+                vk::DynamicState::DEPTH_COMPARE_OP => {
+                    device.cmd_set_depth_compare_op(command_buffer, self.depth_compare_op)
+                }
+                _ => unreachable!(),
+            });
+    }
+}
+
+pub struct PipelineHandle {
+    pub pipeline: vk::Pipeline,
+    pub pipeline_layout: vk::PipelineLayout,
+    /// Layouts of the descriptor sets the shaders read, indexed by set.
+    pub set_layouts: SmallVec<[vk::DescriptorSetLayout; 4]>,
+    /// Stages the push constants must be pushed to.
+    pub push_constant_stages: vk::ShaderStageFlags,
+    pub dynamic_state: DynamicState,
+}
+
+impl PipelineHandle {
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+        }
+    }
+}
+
+/// Compute pipeline of a single compute shader, its layout is reflected from the shader.
+pub struct ComputePipelineHandle {
+    pub pipeline: vk::Pipeline,
+    pub pipeline_layout: vk::PipelineLayout,
+    /// Layouts of the descriptor sets the shader reads, indexed by set.
+    pub set_layouts: SmallVec<[vk::DescriptorSetLayout; 4]>,
+    pub push_constant_stages: vk::ShaderStageFlags,
+    /// Descriptors the shader declares, sorted by set and binding.
+    pub bindings: SmallVec<[DescriptorBinding; 4]>,
+    pub push_constant_range: Option<vk::PushConstantRange>,
+    /// Invocations per workgroup declared by the shader.
+    pub workgroup_size: [u32; 3],
+}
+
+impl ComputePipelineHandle {
+    /// Creates the pipeline from the compiled `.comp` stage of the shader, e.g. `mipmap.comp` for `mipmap`.
+    pub fn new(
+        device: &ash::Device,
+        pipeline_cache: vk::PipelineCache,
+        descriptor_layout_cache: &mut DescriptorLayoutCache,
+        shader_name: &str,
+    ) -> track::Result<Self> {
+        let shader_handle = ShaderHandle::new(device, shader_name).track()?;
+        let pipeline_handle = Self::create_pipeline(
+            device,
+            &shader_handle,
+            pipeline_cache,
+            descriptor_layout_cache,
+            shader_name,
+        );
+
+        shader_handle.destroy(device);
+
+        pipeline_handle
+    }
+
+    /// Workgroups needed to cover `invocations` along each axis.
+    #[inline(always)]
+    pub fn group_counts(&self, invocations: [u32; 3]) -> [u32; 3] {
+        [0, 1, 2].map(|axis| invocations[axis].div_ceil(self.workgroup_size[axis]))
+    }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+        }
     }
+
+    fn create_pipeline(
+        device: &ash::Device,
+        shader_handle: &ShaderHandle,
+        pipeline_cache: vk::PipelineCache,
+        descriptor_layout_cache: &mut DescriptorLayoutCache,
+        shader_name: &str,
+    ) -> track::Result<Self> {
+        info!("Preparing Compute Pipeline `{shader_name}`");
+
+        let [stage] = shader_handle.stages.as_slice() else {
+            return Err(NotAComputeShader(shader_name.to_owned())).track();
+        };
+        let Some(workgroup_size) = stage.reflection.workgroup_size else {
+            return Err(NotAComputeShader(shader_name.to_owned())).track();
+        };
+
+        let (pipeline_layout, set_layouts, push_constant_stages) =
+            create_pipeline_layout(device, shader_handle, descriptor_layout_cache).track()?;
+
+        let pipeline_infos = [vk::ComputePipelineCreateInfo::default()
+            .stage(stage.stage_info())
+            .layout(pipeline_layout)];
+
+        let pipeline =
+            unsafe { device.create_compute_pipelines(pipeline_cache, &pipeline_infos, None) };
+
+        let pipeline = match pipeline {
+            Ok(mut pipelines) => pipelines.remove(Default::default()),
+            Err((_, error)) => {
+                unsafe { device.destroy_pipeline_layout(pipeline_layout, None) };
+
+                return Err(error).track();
+            }
+        };
+
+        Ok(Self {
+            pipeline,
+            pipeline_layout,
+            set_layouts,
+            push_constant_stages,
+            bindings: shader_handle.reflection.bindings.clone(),
+            push_constant_range: shader_handle.reflection.push_constant_range,
+            workgroup_size,
+        })
+    }
+}
+
+/// Shader loaded as a compute pipeline without being a single compute stage.
+#[derive(Debug)]
+pub struct NotAComputeShader(String);
+
+impl std::fmt::Display for NotAComputeShader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Shader `{}` isn't a single compute stage", self.0)
+    }
+}
+
+impl std::error::Error for NotAComputeShader {}
+
+/// Creates the pipeline layout from the descriptor sets and push constants the shaders declare,
+/// returns it with the set layouts and the stages the push constants are pushed to.
+fn create_pipeline_layout(
+    device: &ash::Device,
+    shader_handle: &ShaderHandle,
+    descriptor_layout_cache: &mut DescriptorLayoutCache,
+) -> track::Result<(
+    vk::PipelineLayout,
+    SmallVec<[vk::DescriptorSetLayout; 4]>,
+    vk::ShaderStageFlags,
+)> {
+    let set_layouts = shader_handle
+        .reflection
+        .set_layout_bindings()
+        .iter()
+        .map(|bindings| descriptor_layout_cache.get_or_create(device, bindings))
+        .collect::<track::Result<SmallVec<[_; 4]>>>()?;
+    let push_constant_ranges: SmallVec<[_; 1]> = shader_handle
+        .reflection
+        .push_constant_range
+        .into_iter()
+        .collect();
+    let pipeline_layout_info = vk::PipelineLayoutCreateInfo::default()
+        .set_layouts(&set_layouts)
+        .push_constant_ranges(&push_constant_ranges);
+    let pipeline_layout =
+        unsafe { device.create_pipeline_layout(&pipeline_layout_info, None) }.track()?;
+
+    let push_constant_stages = push_constant_ranges
+        .first()
+        .map_or_else(vk::ShaderStageFlags::empty, |range| range.stage_flags);
+
+    Ok((pipeline_layout, set_layouts, push_constant_stages))
 }
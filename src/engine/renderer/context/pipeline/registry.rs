@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use ash::vk;
+#[cfg(feature = "hot_reload")]
+use tracing::{error, info};
+use track::Context;
+
+use super::{ComputePipelineHandle, DescriptorLayoutCache, PipelineBuilder, PipelineHandle};
+
+/// Index of a pipeline in the [`PipelineRegistry`], stays valid when the pipeline is rebuilt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineId(u32);
+
+/// Index of a compute pipeline in the [`PipelineRegistry`], stays valid when the pipeline is rebuilt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ComputePipelineId(u32);
+
+struct RegisteredPipeline {
+    builder: PipelineBuilder,
+    pipeline_handle: PipelineHandle,
+}
+
+struct RegisteredComputePipeline {
+    shader_name: String,
+    pipeline_handle: ComputePipelineHandle,
+}
+
+/// Graphics pipelines by name, rebuilt from their builders when their shaders change,
+/// and compute pipelines by the name of their shader.
+pub struct PipelineRegistry {
+    pipelines: Vec<RegisteredPipeline>,
+    ids: HashMap<String, PipelineId>,
+    compute_pipelines: Vec<RegisteredComputePipeline>,
+    /// Every pipeline is created through it, it outlives the registry.
+    pipeline_cache: vk::PipelineCache,
+}
+
+impl PipelineRegistry {
+    #[inline]
+    pub fn new(pipeline_cache: vk::PipelineCache) -> Self {
+        Self {
+            pipelines: Vec::new(),
+            ids: HashMap::new(),
+            compute_pipelines: Vec::new(),
+            pipeline_cache,
+        }
+    }
+
+    /// Builds the pipeline and registers it under `name`, replacing the one registered before,
+    /// which must not be in use anymore.
+    pub fn register(
+        &mut self,
+        device: &ash::Device,
+        descriptor_layout_cache: &mut DescriptorLayoutCache,
+        name: &str,
+        builder: PipelineBuilder,
+        target_format: vk::Format,
+    ) -> track::Result<PipelineId> {
+        let pipeline_handle = builder
+            .build(
+                device,
+                self.pipeline_cache,
+                descriptor_layout_cache,
+                target_format,
+            )
+            .track()?;
+
+        if let Some(&id) = self.ids.get(name) {
+            let registered_pipeline = &mut self.pipelines[id.0 as usize];
+            registered_pipeline.pipeline_handle.destroy(device);
+            registered_pipeline.pipeline_handle = pipeline_handle;
+            registered_pipeline.builder = builder;
+
+            return Ok(id);
+        }
+
+        let id = PipelineId(self.pipelines.len() as u32);
+        self.pipelines.push(RegisteredPipeline {
+            builder,
+            pipeline_handle,
+        });
+        self.ids.insert(name.to_owned(), id);
+
+        Ok(id)
+    }
+
+    #[inline(always)]
+    pub fn id(&self, name: &str) -> Option<PipelineId> {
+        self.ids.get(name).copied()
+    }
+
+    #[inline(always)]
+    pub fn get(&self, id: PipelineId) -> &PipelineHandle {
+        &self.pipelines[id.0 as usize].pipeline_handle
+    }
+
+    /// Creates the compute pipeline of the shader, replacing the one registered before,
+    /// which must not be in use anymore.
+    pub fn register_compute(
+        &mut self,
+        device: &ash::Device,
+        descriptor_layout_cache: &mut DescriptorLayoutCache,
+        shader_name: &str,
+    ) -> track::Result<ComputePipelineId> {
+        let pipeline_handle = ComputePipelineHandle::new(
+            device,
+            self.pipeline_cache,
+            descriptor_layout_cache,
+            shader_name,
+        )
+        .track()?;
+
+        if let Some(id) = self.compute_id(shader_name) {
+            let registered_pipeline = &mut self.compute_pipelines[id.0 as usize];
+            registered_pipeline.pipeline_handle.destroy(device);
+            registered_pipeline.pipeline_handle = pipeline_handle;
+
+            return Ok(id);
+        }
+
+        self.compute_pipelines.push(RegisteredComputePipeline {
+            shader_name: shader_name.to_owned(),
+            pipeline_handle,
+        });
+
+        Ok(ComputePipelineId(self.compute_pipelines.len() as u32 - 1))
+    }
+
+    #[inline]
+    pub fn compute_id(&self, shader_name: &str) -> Option<ComputePipelineId> {
+        self.compute_pipelines
+            .iter()
+            .position(|registered_pipeline| registered_pipeline.shader_name == shader_name)
+            .map(|index| ComputePipelineId(index as u32))
+    }
+
+    #[inline(always)]
+    pub fn get_compute(&self, id: ComputePipelineId) -> &ComputePipelineHandle {
+        &self.compute_pipelines[id.0 as usize].pipeline_handle
+    }
+
+    /// Rebuilds the pipelines of the recompiled shaders, none of them may be in use.
+    ///
+    /// A pipeline that fails to build stays as it was, the error is logged.
+    #[cfg(feature = "hot_reload")]
+    pub fn rebuild_with_shaders(
+        &mut self,
+        device: &ash::Device,
+        descriptor_layout_cache: &mut DescriptorLayoutCache,
+        target_format: vk::Format,
+        shader_names: &[String],
+    ) {
+        let pipeline_cache = self.pipeline_cache;
+        self.pipelines
+            .iter_mut()
+            .filter(|registered_pipeline| {
+                shader_names
+                    .iter()
+                    .any(|name| name == registered_pipeline.builder.shader_name())
+            })
+            .for_each(|registered_pipeline| {
+                let shader_name = registered_pipeline.builder.shader_name();
+                let pipeline_handle = registered_pipeline.builder.build(
+                    device,
+                    pipeline_cache,
+                    descriptor_layout_cache,
+                    target_format,
+                );
+
+                match pipeline_handle {
+                    Ok(pipeline_handle) => {
+                        registered_pipeline.pipeline_handle.destroy(device);
+                        registered_pipeline.pipeline_handle = pipeline_handle;
+
+                        info!("Reloaded a pipeline of the `{shader_name}` shader");
+                    }
+                    Err(error) => {
+                        error!(
+                            "Failed to rebuild a pipeline of the `{shader_name}` shader: {error}"
+                        )
+                    }
+                }
+            });
+
+        self.compute_pipelines
+            .iter_mut()
+            .filter(|registered_pipeline| shader_names.contains(&registered_pipeline.shader_name))
+            .for_each(|registered_pipeline| {
+                let shader_name = &registered_pipeline.shader_name;
+                let pipeline_handle = ComputePipelineHandle::new(
+                    device,
+                    pipeline_cache,
+                    descriptor_layout_cache,
+                    shader_name,
+                );
+
+                match pipeline_handle {
+                    Ok(pipeline_handle) => {
+                        registered_pipeline.pipeline_handle.destroy(device);
+                        registered_pipeline.pipeline_handle = pipeline_handle;
+
+                        info!("Reloaded the compute pipeline of the `{shader_name}` shader");
+                    }
+                    Err(error) => error!(
+                        "Failed to rebuild the compute pipeline of the `{shader_name}` shader: {error}"
+                    ),
+                }
+            });
+    }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        self.pipelines
+            .iter()
+            .for_each(|registered_pipeline| registered_pipeline.pipeline_handle.destroy(device));
+        self.compute_pipelines
+            .iter()
+            .for_each(|registered_pipeline| registered_pipeline.pipeline_handle.destroy(device));
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use ash::vk;
+use tracing::{info, warn};
+use track::Context;
+
+use crate::engine::asset_system::binary;
+
+/// Size of `VkPipelineCacheHeaderVersionOne`, the header every pipeline cache starts with.
+const HEADER_SIZE: usize = 16 + vk::UUID_SIZE;
+
+/// Pipeline cache of the driver, loaded from the disk at startup and saved back on shutdown,
+/// so the pipelines compiled by a previous run are created without compiling them again.
+pub struct PipelineCache {
+    pub pipeline_cache: vk::PipelineCache,
+}
+
+impl PipelineCache {
+    /// Creates the cache from the file, or an empty one if the file is missing or was written
+    /// by another device or driver version.
+    pub fn new(
+        device: &ash::Device,
+        device_properties: &vk::PhysicalDeviceProperties,
+        path: &Path,
+    ) -> track::Result<Self> {
+        let initial_data = match std::fs::read(path) {
+            Ok(data) if is_compatible(&data, device_properties) => {
+                info!("Loaded the pipeline cache from {path:?}");
+                data
+            }
+            Ok(_) => {
+                info!("Discarding the pipeline cache written by another device or driver");
+                Vec::new()
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => {
+                warn!("Failed to read the pipeline cache, starting with an empty one: {error}");
+                Vec::new()
+            }
+        };
+
+        let pipeline_cache_info =
+            vk::PipelineCacheCreateInfo::default().initial_data(&initial_data);
+        let pipeline_cache = unsafe { device.create_pipeline_cache(&pipeline_cache_info, None) };
+
+        // NOTE: A cache that passed the header check may still be corrupted past it.
+        let pipeline_cache = match pipeline_cache {
+            Ok(pipeline_cache) => pipeline_cache,
+            Err(error) if !initial_data.is_empty() => {
+                warn!("Failed to load the pipeline cache, starting with an empty one: {error}");
+
+                let pipeline_cache_info = vk::PipelineCacheCreateInfo::default();
+                unsafe { device.create_pipeline_cache(&pipeline_cache_info, None) }.track()?
+            }
+            Err(error) => return Err(error).track(),
+        };
+
+        Ok(Self { pipeline_cache })
+    }
+
+    /// Writes everything the driver has cached so far to the file.
+    pub fn save(&self, device: &ash::Device, path: &Path) -> track::Result<()> {
+        let data = unsafe { device.get_pipeline_cache_data(self.pipeline_cache) }.track()?;
+        binary::write_file(path, &data).track()?;
+
+        info!("Saved the pipeline cache to {path:?}");
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe { device.destroy_pipeline_cache(self.pipeline_cache, None) };
+    }
+}
+
+/// Whether the cache was written by the same device and driver, as drivers may reject or,
+/// worse, misread the caches of others.
+fn is_compatible(data: &[u8], device_properties: &vk::PhysicalDeviceProperties) -> bool {
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+
+    // NOTE: The header is written in the byte order of the host.
+    let u32_at = |offset: usize| u32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap());
+
+    u32_at(0) as usize >= HEADER_SIZE
+        && u32_at(4) == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+        && u32_at(8) == device_properties.vendor_id
+        && u32_at(12) == device_properties.device_id
+        && data[16..HEADER_SIZE] == device_properties.pipeline_cache_uuid
+}
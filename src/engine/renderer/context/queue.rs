@@ -0,0 +1,218 @@
+use ash::vk;
+use smallvec::SmallVec;
+use tracing::info;
+use tracing_unwrap::ResultExt;
+
+use super::surface::SurfaceHandle;
+
+/// Queue family indices picked for every kind of work.
+///
+/// Transfer prefers a dedicated family and falls back to the graphics one. Compute dispatches
+/// are recorded into the frame's command buffer, so they run on the graphics queue.
+#[derive(Clone, Copy, Debug)]
+pub struct QueueFamilies {
+    pub graphics: u32,
+    pub transfer: u32,
+    pub present: Option<u32>,
+}
+
+impl QueueFamilies {
+    /// Returns `None` if the device has no graphics family or, with a surface, no family that can present.
+    pub fn find(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        surface_handle: Option<&SurfaceHandle>,
+    ) -> Option<Self> {
+        let properties =
+            unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
+
+        let supports_present = |queue_family_index: u32| {
+            surface_handle.is_some_and(|surface_handle| unsafe {
+                surface_handle
+                    .surface_loader
+                    .get_physical_device_surface_support(
+                        physical_device,
+                        queue_family_index,
+                        surface_handle.surface,
+                    )
+                    .unwrap_or_log()
+            })
+        };
+
+        let find_family = |required: vk::QueueFlags, excluded: vk::QueueFlags| {
+            properties
+                .iter()
+                .position(|property| {
+                    property.queue_count > 0
+                        && property.queue_flags.contains(required)
+                        && !property.queue_flags.intersects(excluded)
+                })
+                .map(|queue_family_index| queue_family_index as u32)
+        };
+
+        let graphics_families = properties
+            .iter()
+            .enumerate()
+            .filter(|(_, property)| {
+                property.queue_count > 0 && property.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+            })
+            .map(|(queue_family_index, _)| queue_family_index as u32);
+
+        let (graphics, present) = match surface_handle {
+            Some(_) => {
+                let graphics = graphics_families
+                    .clone()
+                    .find(|&queue_family_index| supports_present(queue_family_index));
+
+                match graphics {
+                    Some(graphics) => (graphics, Some(graphics)),
+                    None => (
+                        graphics_families.clone().next()?,
+                        Some((0..properties.len() as u32).find(|&index| supports_present(index))?),
+                    ),
+                }
+            }
+            None => (graphics_families.clone().next()?, None),
+        };
+
+        let transfer = find_family(
+            vk::QueueFlags::TRANSFER,
+            vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE,
+        )
+        .or_else(|| find_family(vk::QueueFlags::TRANSFER, vk::QueueFlags::GRAPHICS))
+        .unwrap_or(graphics);
+
+        Some(Self {
+            graphics,
+            transfer,
+            present,
+        })
+    }
+
+    /// Every distinct family, each needs exactly one `vk::DeviceQueueCreateInfo`.
+    #[inline]
+    pub fn unique(&self) -> SmallVec<[u32; 4]> {
+        let mut families: SmallVec<[u32; 4]> = [self.graphics, self.transfer]
+            .into_iter()
+            .chain(self.present)
+            .collect();
+        families.sort_unstable();
+        families.dedup();
+
+        families
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Queue {
+    pub family_index: u32,
+    pub queue: vk::Queue,
+}
+
+/// Owns the queues of every family the device was created with.
+pub struct QueueManager {
+    pub families: QueueFamilies,
+    pub graphics: Queue,
+    pub transfer: Queue,
+    pub present: Option<Queue>,
+}
+
+impl QueueManager {
+    pub const QUEUE_PRIORITIES: [f32; 1] = [1.0];
+
+    #[inline]
+    pub fn queue_create_infos(
+        families: &QueueFamilies,
+    ) -> SmallVec<[vk::DeviceQueueCreateInfo<'static>; 4]> {
+        families
+            .unique()
+            .into_iter()
+            .map(|queue_family_index| {
+                vk::DeviceQueueCreateInfo::default()
+                    .queue_family_index(queue_family_index)
+                    .queue_priorities(&Self::QUEUE_PRIORITIES)
+            })
+            .collect()
+    }
+
+    pub fn new(device: &ash::Device, families: QueueFamilies) -> Self {
+        let get_queue = |family_index| Queue {
+            family_index,
+            queue: unsafe { device.get_device_queue(family_index, 0) },
+        };
+
+        info!("Queue families: {families:?}");
+
+        Self {
+            families,
+            graphics: get_queue(families.graphics),
+            transfer: get_queue(families.transfer),
+            present: families.present.map(get_queue),
+        }
+    }
+
+    /// Builds the release and acquire halves of a buffer ownership transfer between two families.
+    ///
+    /// The release barrier goes into a command buffer of the `src` family and the acquire one
+    /// into a command buffer of the `dst` family, with a semaphore or fence in between.
+    #[inline]
+    pub fn buffer_ownership_transfer(
+        buffer: vk::Buffer,
+        src: (u32, vk::PipelineStageFlags2, vk::AccessFlags2),
+        dst: (u32, vk::PipelineStageFlags2, vk::AccessFlags2),
+    ) -> (
+        vk::BufferMemoryBarrier2<'static>,
+        vk::BufferMemoryBarrier2<'static>,
+    ) {
+        let (src_family_index, src_stage_mask, src_access_mask) = src;
+        let (dst_family_index, dst_stage_mask, dst_access_mask) = dst;
+
+        let barrier = vk::BufferMemoryBarrier2::default()
+            .src_queue_family_index(src_family_index)
+            .dst_queue_family_index(dst_family_index)
+            .buffer(buffer)
+            .size(vk::WHOLE_SIZE);
+
+        (
+            barrier
+                .src_stage_mask(src_stage_mask)
+                .src_access_mask(src_access_mask),
+            barrier
+                .dst_stage_mask(dst_stage_mask)
+                .dst_access_mask(dst_access_mask),
+        )
+    }
+
+    /// Same as [`QueueManager::buffer_ownership_transfer`] for an image, optionally changing its layout.
+    #[inline]
+    pub fn image_ownership_transfer(
+        image: vk::Image,
+        subresource_range: vk::ImageSubresourceRange,
+        layouts: (vk::ImageLayout, vk::ImageLayout),
+        src: (u32, vk::PipelineStageFlags2, vk::AccessFlags2),
+        dst: (u32, vk::PipelineStageFlags2, vk::AccessFlags2),
+    ) -> (
+        vk::ImageMemoryBarrier2<'static>,
+        vk::ImageMemoryBarrier2<'static>,
+    ) {
+        let (src_family_index, src_stage_mask, src_access_mask) = src;
+        let (dst_family_index, dst_stage_mask, dst_access_mask) = dst;
+
+        let barrier = vk::ImageMemoryBarrier2::default()
+            .src_queue_family_index(src_family_index)
+            .dst_queue_family_index(dst_family_index)
+            .old_layout(layouts.0)
+            .new_layout(layouts.1)
+            .image(image)
+            .subresource_range(subresource_range);
+
+        (
+            barrier
+                .src_stage_mask(src_stage_mask)
+                .src_access_mask(src_access_mask),
+            barrier
+                .dst_stage_mask(dst_stage_mask)
+                .dst_access_mask(dst_access_mask),
+        )
+    }
+}
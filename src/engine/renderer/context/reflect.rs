@@ -0,0 +1,921 @@
+//! Reflection of the SPIR-V modules: entry points, stages, interface variables, descriptor bindings
+//! and push constant blocks are read straight from the module's instructions.
+
+use std::{collections::HashMap, ffi::CString, fmt};
+
+use ash::vk;
+use smallvec::SmallVec;
+
+use crate::engine::asset_system::mesh::VertexDescription;
+
+const MAGIC_NUMBER: u32 = 0x0723_0203;
+const HEADER_LEN: usize = 5;
+
+mod op {
+    pub const ENTRY_POINT: u32 = 15;
+    pub const EXECUTION_MODE: u32 = 16;
+    pub const TYPE_INT: u32 = 21;
+    pub const TYPE_FLOAT: u32 = 22;
+    pub const TYPE_VECTOR: u32 = 23;
+    pub const TYPE_MATRIX: u32 = 24;
+    pub const TYPE_IMAGE: u32 = 25;
+    pub const TYPE_SAMPLER: u32 = 26;
+    pub const TYPE_SAMPLED_IMAGE: u32 = 27;
+    pub const TYPE_ARRAY: u32 = 28;
+    pub const TYPE_RUNTIME_ARRAY: u32 = 29;
+    pub const TYPE_STRUCT: u32 = 30;
+    pub const TYPE_POINTER: u32 = 32;
+    pub const CONSTANT: u32 = 43;
+    pub const VARIABLE: u32 = 59;
+    pub const DECORATE: u32 = 71;
+    pub const MEMBER_DECORATE: u32 = 72;
+}
+
+mod decoration {
+    pub const BUFFER_BLOCK: u32 = 3;
+    pub const ARRAY_STRIDE: u32 = 6;
+    pub const MATRIX_STRIDE: u32 = 7;
+    pub const LOCATION: u32 = 30;
+    pub const BINDING: u32 = 33;
+    pub const DESCRIPTOR_SET: u32 = 34;
+    pub const OFFSET: u32 = 35;
+}
+
+mod execution_mode {
+    pub const LOCAL_SIZE: u32 = 17;
+}
+
+mod storage_class {
+    pub const UNIFORM_CONSTANT: u32 = 0;
+    pub const INPUT: u32 = 1;
+    pub const UNIFORM: u32 = 2;
+    pub const OUTPUT: u32 = 3;
+    pub const PUSH_CONSTANT: u32 = 9;
+    pub const STORAGE_BUFFER: u32 = 12;
+}
+
+mod dim {
+    pub const BUFFER: u32 = 5;
+    pub const SUBPASS_DATA: u32 = 6;
+}
+
+/// Image accessed with loads and stores rather than through a sampler.
+const STORAGE_IMAGE: u32 = 2;
+
+#[derive(Debug)]
+pub enum ReflectionError {
+    InvalidModule,
+    NoEntryPoint,
+    UnsupportedExecutionModel(u32),
+    UnsupportedType {
+        id: u32,
+    },
+    MismatchedBinding {
+        set: u32,
+        binding: u32,
+    },
+    MismatchedInterface {
+        location: u32,
+        stage: vk::ShaderStageFlags,
+    },
+    MismatchedVertexInput {
+        location: u32,
+        format: vk::Format,
+    },
+}
+
+impl fmt::Display for ReflectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidModule => write!(f, "Not a valid SPIR-V module"),
+            Self::NoEntryPoint => write!(f, "SPIR-V module has no entry point"),
+            Self::UnsupportedExecutionModel(execution_model) => {
+                write!(f, "Unsupported execution model: {execution_model}")
+            }
+            Self::UnsupportedType { id } => write!(f, "Unsupported type of the SPIR-V id {id}"),
+            Self::MismatchedBinding { set, binding } => write!(
+                f,
+                "Stages declare different descriptors at set {set}, binding {binding}"
+            ),
+            Self::MismatchedInterface { location, stage } => write!(
+                f,
+                "Input at location {location} of the {stage:?} stage doesn't match \
+                 an output of the previous stage"
+            ),
+            Self::MismatchedVertexInput { location, format } => write!(
+                f,
+                "Vertex input at location {location} of format {format:?} isn't in the vertex layout"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReflectionError {}
+
+/// Input or output variable of a stage, formats the engine can't express are `UNDEFINED`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterfaceVariable {
+    pub location: u32,
+    pub format: vk::Format,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DescriptorBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: vk::DescriptorType,
+    pub count: u32,
+    pub stage_flags: vk::ShaderStageFlags,
+}
+
+/// What a single shader stage declares.
+pub struct ShaderReflection {
+    pub entry_point: CString,
+    pub stage: vk::ShaderStageFlags,
+    pub inputs: SmallVec<[InterfaceVariable; 4]>,
+    pub outputs: SmallVec<[InterfaceVariable; 4]>,
+    pub bindings: SmallVec<[DescriptorBinding; 4]>,
+    pub push_constant_range: Option<vk::PushConstantRange>,
+    /// Invocations per workgroup of a compute shader.
+    pub workgroup_size: Option<[u32; 3]>,
+}
+
+impl ShaderReflection {
+    pub fn new(code: &[u32]) -> Result<Self, ReflectionError> {
+        if code.len() < HEADER_LEN || code[0] != MAGIC_NUMBER {
+            return Err(ReflectionError::InvalidModule);
+        }
+
+        let mut module = Module::default();
+        let mut words = &code[HEADER_LEN..];
+        while let Some(&first_word) = words.first() {
+            let word_count = (first_word >> 16) as usize;
+            if word_count == 0 || word_count > words.len() {
+                return Err(ReflectionError::InvalidModule);
+            }
+
+            module.parse_instruction(first_word & 0xFFFF, &words[1..word_count])?;
+            words = &words[word_count..];
+        }
+
+        module.reflect()
+    }
+}
+
+/// Everything the stages of a pipeline declare, checked against each other.
+pub struct PipelineReflection {
+    /// Bindings of every stage sorted by set and binding, with the stages using them.
+    pub bindings: SmallVec<[DescriptorBinding; 4]>,
+    /// Single range covering the push constants of every stage.
+    pub push_constant_range: Option<vk::PushConstantRange>,
+    pub vertex_inputs: SmallVec<[InterfaceVariable; 4]>,
+}
+
+impl PipelineReflection {
+    /// Merges the stages, every binding must be the same in the stages declaring it and every input
+    /// must be written by the previous stage.
+    pub fn new<'a>(
+        stages: impl IntoIterator<Item = &'a ShaderReflection>,
+    ) -> Result<Self, ReflectionError> {
+        let mut stages: SmallVec<[&ShaderReflection; 2]> = stages.into_iter().collect();
+        // NOTE: Graphics stage flags are ordered like the stages in the pipeline.
+        stages.sort_by_key(|stage| stage.stage.as_raw());
+
+        stages.windows(2).try_for_each(|pair| {
+            pair[1].inputs.iter().try_for_each(|input| {
+                let matches = pair[0].outputs.iter().any(|output| {
+                    output.location == input.location
+                        && (output.format == input.format
+                            || output.format == vk::Format::UNDEFINED
+                            || input.format == vk::Format::UNDEFINED)
+                });
+
+                match matches {
+                    true => Ok(()),
+                    false => Err(ReflectionError::MismatchedInterface {
+                        location: input.location,
+                        stage: pair[1].stage,
+                    }),
+                }
+            })
+        })?;
+
+        let mut bindings = SmallVec::<[DescriptorBinding; 4]>::new();
+        for binding in stages.iter().flat_map(|stage| stage.bindings.iter()) {
+            let existing_binding = bindings.iter_mut().find(|existing_binding| {
+                (existing_binding.set, existing_binding.binding) == (binding.set, binding.binding)
+            });
+
+            match existing_binding {
+                Some(existing_binding)
+                    if (existing_binding.descriptor_type, existing_binding.count)
+                        != (binding.descriptor_type, binding.count) =>
+                {
+                    return Err(ReflectionError::MismatchedBinding {
+                        set: binding.set,
+                        binding: binding.binding,
+                    });
+                }
+                Some(existing_binding) => existing_binding.stage_flags |= binding.stage_flags,
+                None => bindings.push(*binding),
+            }
+        }
+        bindings.sort_by_key(|binding| (binding.set, binding.binding));
+
+        let push_constant_range = stages
+            .iter()
+            .filter_map(|stage| stage.push_constant_range)
+            .reduce(|range, stage_range| {
+                let offset = range.offset.min(stage_range.offset);
+                let end = (range.offset + range.size).max(stage_range.offset + stage_range.size);
+
+                vk::PushConstantRange::default()
+                    .stage_flags(range.stage_flags | stage_range.stage_flags)
+                    .offset(offset)
+                    .size(end - offset)
+            });
+
+        let vertex_inputs = stages
+            .iter()
+            .find(|stage| stage.stage == vk::ShaderStageFlags::VERTEX)
+            .map(|stage| stage.inputs.clone())
+            .unwrap_or_default();
+
+        Ok(Self {
+            bindings,
+            push_constant_range,
+            vertex_inputs,
+        })
+    }
+
+    /// Bindings of every set up to the highest one used, unused sets are empty.
+    pub fn set_layout_bindings(
+        &self,
+    ) -> SmallVec<[SmallVec<[vk::DescriptorSetLayoutBinding<'static>; 4]>; 4]> {
+        let set_count = self.bindings.last().map_or(0, |binding| binding.set + 1);
+
+        (0..set_count)
+            .map(|set| {
+                self.bindings
+                    .iter()
+                    .filter(|binding| binding.set == set)
+                    .map(|binding| {
+                        vk::DescriptorSetLayoutBinding::default()
+                            .binding(binding.binding)
+                            .descriptor_type(binding.descriptor_type)
+                            .descriptor_count(binding.count)
+                            .stage_flags(binding.stage_flags)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Attributes of the vertex layout read by the vertex stage, each input must be in the layout
+    /// with the same format.
+    pub fn vertex_attributes(
+        &self,
+        vertex_description: &VertexDescription,
+    ) -> Result<SmallVec<[vk::VertexInputAttributeDescription; 4]>, ReflectionError> {
+        self.vertex_inputs
+            .iter()
+            .map(|input| {
+                vertex_description
+                    .attributes
+                    .iter()
+                    .find(|attribute| {
+                        attribute.location == input.location && attribute.format == input.format
+                    })
+                    .copied()
+                    .ok_or(ReflectionError::MismatchedVertexInput {
+                        location: input.location,
+                        format: input.format,
+                    })
+            })
+            .collect()
+    }
+}
+
+enum Type {
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Image { dim: u32, sampled: u32 },
+    Sampler,
+    SampledImage,
+    Array { element: u32, length: u32 },
+    RuntimeArray,
+    Struct { members: SmallVec<[u32; 8]> },
+    Pointer { pointee: u32 },
+}
+
+#[derive(Default)]
+struct Decorations {
+    location: Option<u32>,
+    binding: Option<u32>,
+    set: Option<u32>,
+    offset: Option<u32>,
+    array_stride: Option<u32>,
+    matrix_stride: Option<u32>,
+    buffer_block: bool,
+}
+
+struct EntryPoint {
+    execution_model: u32,
+    id: u32,
+    name: CString,
+}
+
+struct Variable {
+    id: u32,
+    pointer_type: u32,
+    storage_class: u32,
+}
+
+/// Instructions of a module the reflection needs, indexed by their result id.
+#[derive(Default)]
+struct Module {
+    entry_point: Option<EntryPoint>,
+    /// Local sizes by the id of their entry point.
+    local_sizes: HashMap<u32, [u32; 3]>,
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, u32>,
+    variables: Vec<Variable>,
+    decorations: HashMap<u32, Decorations>,
+    member_decorations: HashMap<(u32, u32), Decorations>,
+}
+
+impl Module {
+    fn parse_instruction(&mut self, opcode: u32, operands: &[u32]) -> Result<(), ReflectionError> {
+        let operand = |index: usize| {
+            operands
+                .get(index)
+                .copied()
+                .ok_or(ReflectionError::InvalidModule)
+        };
+
+        match opcode {
+            // NOTE: Only the first entry point is reflected, the engine's shaders have a single one.
+            op::ENTRY_POINT if self.entry_point.is_none() => {
+                self.entry_point = Some(EntryPoint {
+                    execution_model: operand(0)?,
+                    id: operand(1)?,
+                    name: literal_string(operands.get(2..).unwrap_or_default())?,
+                });
+            }
+            op::EXECUTION_MODE if operand(1)? == execution_mode::LOCAL_SIZE => {
+                let local_size = [operand(2)?, operand(3)?, operand(4)?];
+                self.local_sizes.insert(operand(0)?, local_size);
+            }
+            op::TYPE_INT => {
+                let ty = Type::Int {
+                    width: operand(1)?,
+                    signed: operand(2)? != 0,
+                };
+                self.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_FLOAT => {
+                let ty = Type::Float { width: operand(1)? };
+                self.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_VECTOR => {
+                let ty = Type::Vector {
+                    component: operand(1)?,
+                    count: operand(2)?,
+                };
+                self.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_MATRIX => {
+                let ty = Type::Matrix {
+                    column: operand(1)?,
+                    count: operand(2)?,
+                };
+                self.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_IMAGE => {
+                let ty = Type::Image {
+                    dim: operand(2)?,
+                    sampled: operand(6)?,
+                };
+                self.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_SAMPLER => {
+                self.types.insert(operand(0)?, Type::Sampler);
+            }
+            op::TYPE_SAMPLED_IMAGE => {
+                self.types.insert(operand(0)?, Type::SampledImage);
+            }
+            op::TYPE_ARRAY => {
+                let length = *self
+                    .constants
+                    .get(&operand(2)?)
+                    .ok_or(ReflectionError::InvalidModule)?;
+                let ty = Type::Array {
+                    element: operand(1)?,
+                    length,
+                };
+                self.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_RUNTIME_ARRAY => {
+                self.types.insert(operand(0)?, Type::RuntimeArray);
+            }
+            op::TYPE_STRUCT => {
+                let ty = Type::Struct {
+                    members: operands.get(1..).unwrap_or_default().into(),
+                };
+                self.types.insert(operand(0)?, ty);
+            }
+            op::TYPE_POINTER => {
+                let ty = Type::Pointer {
+                    pointee: operand(2)?,
+                };
+                self.types.insert(operand(0)?, ty);
+            }
+            // NOTE: Only the low word matters, constants are only read as array lengths.
+            op::CONSTANT => {
+                self.constants.insert(operand(1)?, operand(2)?);
+            }
+            op::VARIABLE => self.variables.push(Variable {
+                id: operand(1)?,
+                pointer_type: operand(0)?,
+                storage_class: operand(2)?,
+            }),
+            op::DECORATE => {
+                let decorations = self.decorations.entry(operand(0)?).or_default();
+                Self::decorate(decorations, operand(1)?, operands.get(2).copied());
+            }
+            op::MEMBER_DECORATE => {
+                let decorations = self
+                    .member_decorations
+                    .entry((operand(0)?, operand(1)?))
+                    .or_default();
+                Self::decorate(decorations, operand(2)?, operands.get(3).copied());
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn decorate(decorations: &mut Decorations, decoration: u32, value: Option<u32>) {
+        match decoration {
+            decoration::LOCATION => decorations.location = value,
+            decoration::BINDING => decorations.binding = value,
+            decoration::DESCRIPTOR_SET => decorations.set = value,
+            decoration::OFFSET => decorations.offset = value,
+            decoration::ARRAY_STRIDE => decorations.array_stride = value,
+            decoration::MATRIX_STRIDE => decorations.matrix_stride = value,
+            decoration::BUFFER_BLOCK => decorations.buffer_block = true,
+            _ => (),
+        }
+    }
+
+    fn reflect(self) -> Result<ShaderReflection, ReflectionError> {
+        let entry_point = self
+            .entry_point
+            .as_ref()
+            .ok_or(ReflectionError::NoEntryPoint)?;
+        let stage = match entry_point.execution_model {
+            0 => vk::ShaderStageFlags::VERTEX,
+            1 => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+            2 => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+            3 => vk::ShaderStageFlags::GEOMETRY,
+            4 => vk::ShaderStageFlags::FRAGMENT,
+            5 => vk::ShaderStageFlags::COMPUTE,
+            execution_model => {
+                return Err(ReflectionError::UnsupportedExecutionModel(execution_model))
+            }
+        };
+
+        let mut inputs = SmallVec::<[InterfaceVariable; 4]>::new();
+        let mut outputs = SmallVec::<[InterfaceVariable; 4]>::new();
+        let mut bindings = SmallVec::<[DescriptorBinding; 4]>::new();
+        let mut push_constant_range = None;
+
+        for variable in self.variables.iter() {
+            let pointee = match self.types.get(&variable.pointer_type) {
+                Some(Type::Pointer { pointee }) => *pointee,
+                _ => return Err(ReflectionError::InvalidModule),
+            };
+            let decorations = self.decorations.get(&variable.id);
+
+            match variable.storage_class {
+                storage_class::INPUT | storage_class::OUTPUT => {
+                    // NOTE: Built-ins like `gl_Position` have no location, neither do their blocks.
+                    let Some(location) = decorations.and_then(|decorations| decorations.location)
+                    else {
+                        continue;
+                    };
+
+                    let interface_variable = InterfaceVariable {
+                        location,
+                        format: self.format(pointee).unwrap_or(vk::Format::UNDEFINED),
+                    };
+                    match variable.storage_class {
+                        storage_class::INPUT => inputs.push(interface_variable),
+                        _ => outputs.push(interface_variable),
+                    }
+                }
+                storage_class::UNIFORM
+                | storage_class::UNIFORM_CONSTANT
+                | storage_class::STORAGE_BUFFER => {
+                    let (Some(set), Some(binding)) = (
+                        decorations.and_then(|decorations| decorations.set),
+                        decorations.and_then(|decorations| decorations.binding),
+                    ) else {
+                        continue;
+                    };
+
+                    let (descriptor_type, count) =
+                        self.descriptor(pointee, variable.storage_class)?;
+                    bindings.push(DescriptorBinding {
+                        set,
+                        binding,
+                        descriptor_type,
+                        count,
+                        stage_flags: stage,
+                    });
+                }
+                storage_class::PUSH_CONSTANT => {
+                    let Some(Type::Struct { members }) = self.types.get(&pointee) else {
+                        return Err(ReflectionError::UnsupportedType { id: pointee });
+                    };
+
+                    let offset = (0..members.len() as u32)
+                        .filter_map(|member| self.member_decorations(pointee, member).offset)
+                        .min()
+                        .unwrap_or_default();
+                    let end = self.size(pointee, None)?;
+
+                    push_constant_range = Some(
+                        vk::PushConstantRange::default()
+                            .stage_flags(stage)
+                            .offset(offset)
+                            .size(end - offset),
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        inputs.sort_by_key(|input| input.location);
+        outputs.sort_by_key(|output| output.location);
+        bindings.sort_by_key(|binding| (binding.set, binding.binding));
+
+        Ok(ShaderReflection {
+            entry_point: entry_point.name.clone(),
+            stage,
+            inputs,
+            outputs,
+            bindings,
+            push_constant_range,
+            workgroup_size: self.local_sizes.get(&entry_point.id).copied(),
+        })
+    }
+
+    fn format(&self, id: u32) -> Option<vk::Format> {
+        let (component, count) = match self.types.get(&id)? {
+            Type::Vector { component, count } => (*component, *count as usize),
+            _ => (id, 1),
+        };
+
+        let formats = match self.types.get(&component)? {
+            Type::Float { width: 32 } => [
+                vk::Format::R32_SFLOAT,
+                vk::Format::R32G32_SFLOAT,
+                vk::Format::R32G32B32_SFLOAT,
+                vk::Format::R32G32B32A32_SFLOAT,
+            ],
+            Type::Float { width: 64 } => [
+                vk::Format::R64_SFLOAT,
+                vk::Format::R64G64_SFLOAT,
+                vk::Format::R64G64B64_SFLOAT,
+                vk::Format::R64G64B64A64_SFLOAT,
+            ],
+            Type::Int {
+                width: 32,
+                signed: true,
+            } => [
+                vk::Format::R32_SINT,
+                vk::Format::R32G32_SINT,
+                vk::Format::R32G32B32_SINT,
+                vk::Format::R32G32B32A32_SINT,
+            ],
+            Type::Int {
+                width: 32,
+                signed: false,
+            } => [
+                vk::Format::R32_UINT,
+                vk::Format::R32G32_UINT,
+                vk::Format::R32G32B32_UINT,
+                vk::Format::R32G32B32A32_UINT,
+            ],
+            _ => return None,
+        };
+
+        formats.get(count.checked_sub(1)?).copied()
+    }
+
+    fn descriptor(
+        &self,
+        id: u32,
+        storage_class: u32,
+    ) -> Result<(vk::DescriptorType, u32), ReflectionError> {
+        let descriptor_type = match self.types.get(&id) {
+            Some(Type::Array { element, length }) => {
+                let (descriptor_type, count) = self.descriptor(*element, storage_class)?;
+
+                return Ok((descriptor_type, count * length));
+            }
+            Some(Type::Struct { .. })
+                if storage_class == storage_class::STORAGE_BUFFER
+                    || self
+                        .decorations
+                        .get(&id)
+                        .is_some_and(|decorations| decorations.buffer_block) =>
+            {
+                vk::DescriptorType::STORAGE_BUFFER
+            }
+            Some(Type::Struct { .. }) => vk::DescriptorType::UNIFORM_BUFFER,
+            Some(Type::SampledImage) => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            Some(Type::Sampler) => vk::DescriptorType::SAMPLER,
+            Some(Type::Image { dim, sampled }) => match (*dim, *sampled) {
+                (dim::BUFFER, STORAGE_IMAGE) => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+                (dim::BUFFER, _) => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+                (dim::SUBPASS_DATA, _) => vk::DescriptorType::INPUT_ATTACHMENT,
+                (_, STORAGE_IMAGE) => vk::DescriptorType::STORAGE_IMAGE,
+                _ => vk::DescriptorType::SAMPLED_IMAGE,
+            },
+            // NOTE: Runtime arrays need descriptor indexing, which the engine doesn't enable.
+            _ => return Err(ReflectionError::UnsupportedType { id }),
+        };
+
+        Ok((descriptor_type, 1))
+    }
+
+    /// Size of the type in a buffer, matrices of struct members use the member's stride.
+    fn size(&self, id: u32, matrix_stride: Option<u32>) -> Result<u32, ReflectionError> {
+        match self.types.get(&id) {
+            Some(Type::Int { width, .. } | Type::Float { width }) => Ok(width / 8),
+            Some(Type::Vector { component, count }) => Ok(self.size(*component, None)? * count),
+            Some(Type::Matrix { column, count }) => {
+                let column_size = match matrix_stride {
+                    Some(matrix_stride) => matrix_stride,
+                    None => self.size(*column, None)?,
+                };
+
+                Ok(column_size * count)
+            }
+            Some(Type::Array { element, length }) => {
+                let stride = match self
+                    .decorations
+                    .get(&id)
+                    .and_then(|decorations| decorations.array_stride)
+                {
+                    Some(array_stride) => array_stride,
+                    None => self.size(*element, matrix_stride)?,
+                };
+
+                Ok(stride * length)
+            }
+            Some(Type::Struct { members }) => {
+                members
+                    .iter()
+                    .enumerate()
+                    .try_fold(0, |size, (member, &member_type)| {
+                        let decorations = self.member_decorations(id, member as u32);
+                        let end = decorations.offset.unwrap_or_default()
+                            + self.size(member_type, decorations.matrix_stride)?;
+
+                        Ok(size.max(end))
+                    })
+            }
+            _ => Err(ReflectionError::UnsupportedType { id }),
+        }
+    }
+
+    #[inline]
+    fn member_decorations(&self, id: u32, member: u32) -> &Decorations {
+        const NO_DECORATIONS: &Decorations = &Decorations {
+            location: None,
+            binding: None,
+            set: None,
+            offset: None,
+            array_stride: None,
+            matrix_stride: None,
+            buffer_block: false,
+        };
+
+        self.member_decorations
+            .get(&(id, member))
+            .unwrap_or(NO_DECORATIONS)
+    }
+}
+
+/// Reads a nul-terminated UTF-8 string packed into little-endian words.
+fn literal_string(words: &[u32]) -> Result<CString, ReflectionError> {
+    let bytes: Vec<u8> = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .take_while(|&byte| byte != 0)
+        .collect();
+
+    CString::new(bytes).map_err(|_| ReflectionError::InvalidModule)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// Reflects a shader compiled by the build script, e.g. `mesh.vert`.
+    fn reflect(file_name: &str) -> ShaderReflection {
+        let path = Path::new(env!("COMPILED_SHADERS_DIR")).join(format!("{file_name}.spv"));
+        let code = ash::util::read_spv(&mut std::fs::File::open(path).unwrap()).unwrap();
+
+        ShaderReflection::new(&code).unwrap()
+    }
+
+    #[inline]
+    fn variable(location: u32, format: vk::Format) -> InterfaceVariable {
+        InterfaceVariable { location, format }
+    }
+
+    #[inline]
+    fn binding(
+        set: u32,
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+        stage_flags: vk::ShaderStageFlags,
+    ) -> DescriptorBinding {
+        DescriptorBinding {
+            set,
+            binding,
+            descriptor_type,
+            count: 1,
+            stage_flags,
+        }
+    }
+
+    #[test]
+    fn vertex_stage() {
+        let reflection = reflect("mesh.vert");
+
+        assert_eq!(reflection.entry_point.to_str(), Ok("main"));
+        assert_eq!(reflection.stage, vk::ShaderStageFlags::VERTEX);
+        assert_eq!(
+            reflection.inputs.as_slice(),
+            [
+                variable(0, vk::Format::R32G32B32_SFLOAT),
+                variable(1, vk::Format::R32G32B32_SFLOAT),
+            ]
+        );
+        assert_eq!(
+            reflection.outputs.as_slice(),
+            [variable(0, vk::Format::R32G32B32_SFLOAT)]
+        );
+        assert_eq!(
+            reflection.bindings.as_slice(),
+            [binding(
+                0,
+                0,
+                vk::DescriptorType::UNIFORM_BUFFER,
+                vk::ShaderStageFlags::VERTEX
+            )]
+        );
+
+        let push_constant_range = reflection.push_constant_range.unwrap();
+        assert_eq!(
+            push_constant_range.stage_flags,
+            vk::ShaderStageFlags::VERTEX
+        );
+        assert_eq!(push_constant_range.offset, 0);
+        // NOTE: A single `mat4`.
+        assert_eq!(push_constant_range.size, 64);
+        assert_eq!(reflection.workgroup_size, None);
+    }
+
+    #[test]
+    fn fragment_stage() {
+        let reflection = reflect("textured_mesh.frag");
+
+        assert_eq!(reflection.stage, vk::ShaderStageFlags::FRAGMENT);
+        // NOTE: The unused color input may be optimized away.
+        assert!(reflection
+            .inputs
+            .contains(&variable(1, vk::Format::R32G32_SFLOAT)));
+        assert_eq!(
+            reflection.outputs.as_slice(),
+            [variable(0, vk::Format::R32G32B32A32_SFLOAT)]
+        );
+        assert_eq!(
+            reflection.bindings.as_slice(),
+            [binding(
+                1,
+                0,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                vk::ShaderStageFlags::FRAGMENT
+            )]
+        );
+        assert!(reflection.push_constant_range.is_none());
+    }
+
+    #[test]
+    fn compute_stage() {
+        let reflection = reflect("mipmap.comp");
+
+        assert_eq!(reflection.stage, vk::ShaderStageFlags::COMPUTE);
+        assert_eq!(reflection.workgroup_size, Some([8, 8, 1]));
+        assert_eq!(
+            reflection.bindings.as_slice(),
+            [
+                binding(
+                    0,
+                    0,
+                    vk::DescriptorType::STORAGE_IMAGE,
+                    vk::ShaderStageFlags::COMPUTE
+                ),
+                binding(
+                    0,
+                    1,
+                    vk::DescriptorType::STORAGE_IMAGE,
+                    vk::ShaderStageFlags::COMPUTE
+                ),
+            ]
+        );
+        assert!(reflection.push_constant_range.is_none());
+    }
+
+    #[test]
+    fn pipeline_merges_stages() {
+        let vertex = reflect("textured_mesh.vert");
+        let fragment = reflect("textured_mesh.frag");
+        let pipeline = PipelineReflection::new([&fragment, &vertex]).unwrap();
+
+        assert_eq!(
+            pipeline.bindings.as_slice(),
+            [
+                binding(
+                    0,
+                    0,
+                    vk::DescriptorType::UNIFORM_BUFFER,
+                    vk::ShaderStageFlags::VERTEX
+                ),
+                binding(
+                    1,
+                    0,
+                    vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    vk::ShaderStageFlags::FRAGMENT
+                ),
+            ]
+        );
+        assert_eq!(pipeline.set_layout_bindings().len(), 2);
+
+        let push_constant_range = pipeline.push_constant_range.unwrap();
+        assert_eq!(
+            push_constant_range.stage_flags,
+            vk::ShaderStageFlags::VERTEX
+        );
+        assert_eq!(push_constant_range.size, 64);
+
+        let vertex_attributes = pipeline
+            .vertex_attributes(&VertexDescription::new())
+            .unwrap();
+        assert_eq!(
+            vertex_attributes
+                .iter()
+                .map(|attribute| attribute.location)
+                .collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn mismatched_interface() {
+        let vertex = reflect("mesh.vert");
+        let fragment = reflect("textured_mesh.frag");
+
+        assert!(matches!(
+            PipelineReflection::new([&vertex, &fragment]),
+            Err(ReflectionError::MismatchedInterface {
+                location: 1,
+                stage: vk::ShaderStageFlags::FRAGMENT,
+            })
+        ));
+    }
+
+    #[test]
+    fn invalid_module() {
+        assert!(matches!(
+            ShaderReflection::new(&[0; HEADER_LEN]),
+            Err(ReflectionError::InvalidModule)
+        ));
+        assert!(matches!(
+            ShaderReflection::new(&[MAGIC_NUMBER, 0, 0, 0, 0, 0xFFFF_0000]),
+            Err(ReflectionError::InvalidModule)
+        ));
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use ash::vk;
+use track::Context;
+
+/// Parameters a sampler is created and cached by.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SamplerDescription {
+    pub filter: vk::Filter,
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    pub address_mode: vk::SamplerAddressMode,
+}
+
+impl SamplerDescription {
+    pub const LINEAR_REPEAT: Self = Self {
+        filter: vk::Filter::LINEAR,
+        mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+        address_mode: vk::SamplerAddressMode::REPEAT,
+    };
+}
+
+/// Creates every distinct sampler once and hands out the cached one afterwards.
+#[derive(Default)]
+pub struct SamplerCache {
+    samplers: HashMap<SamplerDescription, vk::Sampler>,
+}
+
+impl SamplerCache {
+    pub fn get_or_create(
+        &mut self,
+        device: &ash::Device,
+        description: SamplerDescription,
+    ) -> track::Result<vk::Sampler> {
+        if let Some(&sampler) = self.samplers.get(&description) {
+            return Ok(sampler);
+        }
+
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(description.filter)
+            .min_filter(description.filter)
+            .mipmap_mode(description.mipmap_mode)
+            .address_mode_u(description.address_mode)
+            .address_mode_v(description.address_mode)
+            .address_mode_w(description.address_mode)
+            .max_lod(vk::LOD_CLAMP_NONE);
+
+        let sampler = unsafe { device.create_sampler(&sampler_info, None).track()? };
+
+        self.samplers.insert(description, sampler);
+
+        Ok(sampler)
+    }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        self.samplers.values().for_each(|&sampler| unsafe {
+            device.destroy_sampler(sampler, None);
+        });
+    }
+}
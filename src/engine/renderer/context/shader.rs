@@ -1,67 +1,113 @@
-use crate::engine::utils::cstring::cstr;
+use crate::engine::utils::paths;
 use ash::vk;
 use smallvec::SmallVec;
-use std::{ffi::CStr, path::Path};
+use std::path::Path;
 use tracing_unwrap::OptionExt;
 use track::Context;
 use walkdir::WalkDir;
 
-pub const SHADER_ENTRY_NAME: &CStr = cstr!("main");
+use super::reflect::{PipelineReflection, ShaderReflection};
+
+/// Shader module together with what it declares.
+pub struct ShaderStage {
+    pub shader_module: vk::ShaderModule,
+    pub reflection: ShaderReflection,
+}
+
+impl ShaderStage {
+    #[inline(always)]
+    pub fn stage_info(&self) -> vk::PipelineShaderStageCreateInfo<'_> {
+        vk::PipelineShaderStageCreateInfo::default()
+            .name(&self.reflection.entry_point)
+            .stage(self.reflection.stage)
+            .module(self.shader_module)
+    }
+}
 
 pub struct ShaderHandle {
-    pub shader_modules: SmallVec<[(vk::ShaderModule, vk::ShaderStageFlags); 2]>,
+    pub stages: SmallVec<[ShaderStage; 2]>,
+    /// Descriptor bindings, push constants and vertex inputs of every stage together.
+    pub reflection: PipelineReflection,
 }
 
 impl ShaderHandle {
-    pub fn new(device: &ash::Device) -> Self {
-        let shader_modules = WalkDir::new(r"src\engine\renderer\shaders\spv")
+    /// Loads every compiled stage of the shader, e.g. `mesh.vert.spv` and `mesh.frag.spv` for `mesh`,
+    /// failing if there is none.
+    ///
+    /// Stages are taken from the modules themselves, which must agree on their bindings and interfaces.
+    pub fn new(device: &ash::Device, name: &str) -> track::Result<Self> {
+        let stages = WalkDir::new(paths::shaders_dir())
             .into_iter()
             .filter_map(|entry| {
                 entry
                     .ok()
                     .and_then(|entry| entry.path().is_file().then(|| entry.path().to_owned()))
             })
-            .map(|path| {
-                let filename_parts: Vec<&str> = path
-                    .file_name()
-                    .unwrap_or_log()
-                    .to_str()
-                    .unwrap()
-                    .split('.')
-                    .collect();
-
-                print!("TEST");
-
-                let shader_stage_flags = match filename_parts.as_slice() {
-                    [_, "vert", _] => vk::ShaderStageFlags::VERTEX,
-                    [_, "frag", _] => vk::ShaderStageFlags::FRAGMENT,
-                    _ => panic!(
-                        "Unknown shader type: {}",
-                        path.file_name()
-                            .unwrap_or_log()
-                            .to_string_lossy()
-                            .into_owned()
-                    ),
-                };
-
-                (
-                    Self::create_shader_module(device, &path).unwrap(),
-                    shader_stage_flags,
-                )
+            .filter(|path| {
+                path.extension().is_some_and(|extension| extension == "spv")
+                    && path
+                        .file_name()
+                        .unwrap_or_log()
+                        .to_str()
+                        .unwrap()
+                        .split('.')
+                        .next()
+                        == Some(name)
             })
-            .collect::<SmallVec<[(vk::ShaderModule, vk::ShaderStageFlags); 2]>>();
+            .map(|path| Self::create_stage(device, &path))
+            .collect::<SmallVec<[track::Result<ShaderStage>; 2]>>();
+
+        // NOTE: Stages that loaded are destroyed if another one failed, they would leak otherwise.
+        if stages.iter().any(Result::is_err) {
+            let mut first_error = None;
+            stages.into_iter().for_each(|stage| match stage {
+                Ok(stage) => unsafe { device.destroy_shader_module(stage.shader_module, None) },
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                }
+            });
+
+            return Err(first_error.unwrap());
+        }
+
+        if stages.is_empty() {
+            return Err(NoCompiledStages(name.to_owned())).track();
+        }
+
+        let stages: SmallVec<[ShaderStage; 2]> = stages.into_iter().map(Result::unwrap).collect();
+
+        let reflection = PipelineReflection::new(stages.iter().map(|stage| &stage.reflection));
+        let reflection = match reflection {
+            Ok(reflection) => reflection,
+            Err(error) => {
+                stages.iter().for_each(|stage| unsafe {
+                    device.destroy_shader_module(stage.shader_module, None)
+                });
 
-        assert!(
-            !shader_modules.is_empty(),
-            "Found no shaders in the specified path"
-        );
+                return Err(error).track();
+            }
+        };
+
+        Ok(Self { stages, reflection })
+    }
+
+    #[inline]
+    pub fn stage_infos(&self) -> SmallVec<[vk::PipelineShaderStageCreateInfo<'_>; 2]> {
+        self.stages.iter().map(ShaderStage::stage_info).collect()
+    }
 
-        Self { shader_modules }
+    /// Shader modules are only needed to create pipelines, they can be destroyed right after.
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        self.stages
+            .iter()
+            .for_each(|stage| unsafe { device.destroy_shader_module(stage.shader_module, None) });
     }
 
-    fn create_shader_module(device: &ash::Device, path: &Path) -> track::Result<vk::ShaderModule> {
+    fn create_stage(device: &ash::Device, path: &Path) -> track::Result<ShaderStage> {
         let mut file = std::fs::File::open(path).track()?;
         let decoded = ash::util::read_spv(&mut file).track()?;
+        let reflection = ShaderReflection::new(&decoded).track()?;
 
         let shader_module_info = vk::ShaderModuleCreateInfo::default().code(&decoded);
         let shader_module = unsafe {
@@ -70,6 +116,21 @@ impl ShaderHandle {
                 .track()?
         };
 
-        Ok(shader_module)
+        Ok(ShaderStage {
+            shader_module,
+            reflection,
+        })
     }
 }
+
+/// Shader name without any compiled stage in the shaders directory, e.g. a misspelled one.
+#[derive(Debug)]
+pub struct NoCompiledStages(String);
+
+impl std::fmt::Display for NoCompiledStages {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No compiled stages for `{}`", self.0)
+    }
+}
+
+impl std::error::Error for NoCompiledStages {}
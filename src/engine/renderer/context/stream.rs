@@ -0,0 +1,131 @@
+use ash::vk;
+use track::Context;
+
+use super::{command::Command, queue::QueueManager};
+
+/// Command buffers for uploads the CPU doesn't wait on, polled with [`UploadStream::is_complete`].
+///
+/// Copies go to the transfer queue, the graphics queue waits for them on a semaphore
+/// before acquiring the resources and generating mip chains.
+pub struct UploadStream {
+    pub transfer_command: Command,
+    pub graphics_command: Command,
+    pub transfer_semaphore: vk::Semaphore,
+    pub fence: vk::Fence,
+}
+
+impl UploadStream {
+    pub fn new(device: &ash::Device, queues: &QueueManager) -> track::Result<Self> {
+        let transfer_command = Command::new(device, queues.transfer.family_index, 1).track()?;
+        let graphics_command = Command::new(device, queues.graphics.family_index, 1).track()?;
+
+        let semaphore_info = vk::SemaphoreCreateInfo::default();
+        let transfer_semaphore = unsafe { device.create_semaphore(&semaphore_info, None).track()? };
+
+        let fence_info = vk::FenceCreateInfo::default();
+        let fence = unsafe { device.create_fence(&fence_info, None).track()? };
+
+        Ok(Self {
+            transfer_command,
+            graphics_command,
+            transfer_semaphore,
+            fence,
+        })
+    }
+
+    /// Records and submits the upload without waiting for it.
+    ///
+    /// Without `record_transfer` everything is recorded by `record_graphics` into a single submission.
+    pub unsafe fn submit<T: FnOnce(vk::CommandBuffer), G: FnOnce(vk::CommandBuffer)>(
+        &self,
+        device: &ash::Device,
+        queues: &QueueManager,
+        record_transfer: Option<T>,
+        record_graphics: G,
+    ) -> track::Result<()> {
+        let transfer_semaphores = [self.transfer_semaphore];
+        let wait_dst_stage_masks = [vk::PipelineStageFlags::ALL_COMMANDS];
+
+        let has_transfer = record_transfer.is_some();
+        if let Some(record_transfer) = record_transfer {
+            let command_buffers =
+                [Self::record(device, &self.transfer_command, record_transfer).track()?];
+            let submit_info = vk::SubmitInfo::default()
+                .command_buffers(&command_buffers)
+                .signal_semaphores(&transfer_semaphores);
+
+            device
+                .queue_submit(queues.transfer.queue, &[submit_info], vk::Fence::null())
+                .track()?;
+        }
+
+        let command_buffers =
+            [Self::record(device, &self.graphics_command, record_graphics).track()?];
+        let submit_info = vk::SubmitInfo::default().command_buffers(&command_buffers);
+        let submit_info = match has_transfer {
+            true => submit_info
+                .wait_semaphores(&transfer_semaphores)
+                .wait_dst_stage_mask(&wait_dst_stage_masks),
+            false => submit_info,
+        };
+
+        device
+            .queue_submit(queues.graphics.queue, &[submit_info], self.fence)
+            .track()
+    }
+
+    #[inline(always)]
+    pub unsafe fn is_complete(&self, device: &ash::Device) -> track::Result<bool> {
+        device.get_fence_status(self.fence).track()
+    }
+
+    /// Blocks until the submitted upload finished and makes the stream ready for the next one.
+    #[inline]
+    pub unsafe fn wait(&self, device: &ash::Device) -> track::Result<()> {
+        device
+            .wait_for_fences(&[self.fence], true, u64::MAX)
+            .track()?;
+
+        self.reset(device).track()
+    }
+
+    /// Makes the stream ready for the next upload, the submitted one must be complete.
+    #[inline(always)]
+    pub unsafe fn reset(&self, device: &ash::Device) -> track::Result<()> {
+        device.reset_fences(&[self.fence]).track()
+    }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            device.destroy_command_pool(self.transfer_command.command_pool, None);
+            device.destroy_command_pool(self.graphics_command.command_pool, None);
+            device.destroy_semaphore(self.transfer_semaphore, None);
+            device.destroy_fence(self.fence, None);
+        }
+    }
+
+    unsafe fn record<F: FnOnce(vk::CommandBuffer)>(
+        device: &ash::Device,
+        command: &Command,
+        record: F,
+    ) -> track::Result<vk::CommandBuffer> {
+        let command_buffer = command.command_buffers[0];
+
+        device
+            .reset_command_pool(command.command_pool, vk::CommandPoolResetFlags::empty())
+            .track()?;
+
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo::default()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        device
+            .begin_command_buffer(command_buffer, &command_buffer_begin_info)
+            .track()?;
+
+        record(command_buffer);
+
+        device.end_command_buffer(command_buffer).track()?;
+
+        Ok(command_buffer)
+    }
+}
@@ -18,21 +18,23 @@ impl SwapchainHandle {
         instance: &ash::Instance,
         device_handle: &super::device::DeviceHandle,
         surface_handle: &super::surface::SurfaceHandle,
-        window: &winit::window::Window,
+        window_extent: vk::Extent2D,
     ) -> track::Result<Self> {
         let min_image_count = Self::choose_min_image_count(device_handle.surface_capabilities);
-        let image_extent =
-            Self::choose_extent(device_handle.surface_capabilities, window.inner_size());
+        let image_extent = Self::choose_extent(device_handle.surface_capabilities, window_extent);
 
-        let (swapchain_loader, swapchain) = Self::create_swapchain(
-            instance,
-            &device_handle.device,
+        let swapchain_loader = khr::Swapchain::new(instance, &device_handle.device);
+
+        let swapchain = Self::create_swapchain(
+            &swapchain_loader,
             device_handle.surface_format,
             device_handle.present_mode,
             device_handle.surface_capabilities,
             surface_handle,
             min_image_count,
             image_extent,
+            &Self::queue_family_indices(device_handle),
+            vk::SwapchainKHR::null(),
         )
         .track()?;
 
@@ -52,18 +54,80 @@ impl SwapchainHandle {
             image_extent,
         })
     }
+
+    /// Rebuilds the swapchain and its image views for the new window extent.
+    ///
+    /// The caller must make sure the old images are no longer in use by the GPU.
+    pub fn recreate(
+        &mut self,
+        device_handle: &super::device::DeviceHandle,
+        surface_handle: &super::surface::SurfaceHandle,
+        window_extent: vk::Extent2D,
+    ) -> track::Result<()> {
+        let min_image_count = Self::choose_min_image_count(device_handle.surface_capabilities);
+        let image_extent = Self::choose_extent(device_handle.surface_capabilities, window_extent);
+
+        let swapchain = Self::create_swapchain(
+            &self.swapchain_loader,
+            device_handle.surface_format,
+            device_handle.present_mode,
+            device_handle.surface_capabilities,
+            surface_handle,
+            min_image_count,
+            image_extent,
+            &Self::queue_family_indices(device_handle),
+            self.swapchain,
+        )
+        .track()?;
+
+        self.destroy(&device_handle.device);
+
+        let (images, image_views) = Self::create_images(
+            &device_handle.device,
+            device_handle.surface_format.format,
+            &self.swapchain_loader,
+            swapchain,
+        )
+        .track()?;
+
+        self.swapchain = swapchain;
+        self.images = images;
+        self.image_views = image_views;
+        self.image_extent = image_extent;
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn destroy(&self, device: &ash::Device) {
+        unsafe {
+            self.image_views.iter().for_each(|&image_view| {
+                device.destroy_image_view(image_view, None);
+            });
+
+            self.swapchain_loader
+                .destroy_swapchain(self.swapchain, None);
+        }
+    }
+
     #[inline]
     #[allow(clippy::too_many_arguments)]
     fn create_swapchain(
-        instance: &ash::Instance,
-        device: &ash::Device,
+        swapchain_loader: &khr::Swapchain,
         surface_format: vk::SurfaceFormatKHR,
         present_mode: vk::PresentModeKHR,
         surface_capabilities: vk::SurfaceCapabilitiesKHR,
         surface_handle: &SurfaceHandle,
         min_image_count: u32,
         image_extent: vk::Extent2D,
-    ) -> track::Result<(khr::Swapchain, vk::SwapchainKHR)> {
+        queue_family_indices: &[u32],
+        old_swapchain: vk::SwapchainKHR,
+    ) -> track::Result<vk::SwapchainKHR> {
+        let image_sharing_mode = match queue_family_indices.len() {
+            1 => vk::SharingMode::EXCLUSIVE,
+            _ => vk::SharingMode::CONCURRENT,
+        };
+
         let swapchain_info = vk::SwapchainCreateInfoKHR::default()
             .surface(surface_handle.surface)
             .image_format(surface_format.format)
@@ -73,34 +137,53 @@ impl SwapchainHandle {
             .image_array_layers(1)
             .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
             .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
-            .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .image_sharing_mode(image_sharing_mode)
+            .queue_family_indices(queue_family_indices)
             .image_extent(image_extent)
             .pre_transform(surface_capabilities.current_transform)
+            .old_swapchain(old_swapchain)
             .clipped(true);
 
-        let swapchain_loader = ash::extensions::khr::Swapchain::new(instance, device);
-
         let swapchain = unsafe {
             swapchain_loader
                 .create_swapchain(&swapchain_info, None)
                 .track()?
         };
 
-        Ok((swapchain_loader, swapchain))
+        Ok(swapchain)
+    }
+
+    /// Graphics renders into the swapchain images and present shows them, possibly from another family.
+    #[inline(always)]
+    fn queue_family_indices(device_handle: &super::device::DeviceHandle) -> SmallVec<[u32; 2]> {
+        let families = device_handle.queues.families;
+
+        match families.present {
+            Some(present) if present != families.graphics => {
+                SmallVec::from_buf([families.graphics, present])
+            }
+            _ => SmallVec::from_slice(&[families.graphics]),
+        }
     }
 
     #[inline(always)]
     fn choose_extent(
         surface_capabilities: vk::SurfaceCapabilitiesKHR,
-        window_extent: winit::dpi::PhysicalSize<u32>,
+        window_extent: vk::Extent2D,
     ) -> vk::Extent2D {
         match surface_capabilities.current_extent {
             vk::Extent2D {
                 width: u32::MAX,
                 height: u32::MAX,
             } => vk::Extent2D {
-                width: window_extent.width,
-                height: window_extent.height,
+                width: window_extent.width.clamp(
+                    surface_capabilities.min_image_extent.width,
+                    surface_capabilities.max_image_extent.width,
+                ),
+                height: window_extent.height.clamp(
+                    surface_capabilities.min_image_extent.height,
+                    surface_capabilities.max_image_extent.height,
+                ),
             },
             extent => extent,
         }
@@ -111,7 +194,7 @@ impl SwapchainHandle {
         let max_image_count = surface_capabilities.max_image_count;
 
         let mut min_image_count = surface_capabilities.min_image_count + 1;
-        if min_image_count > 0 && min_image_count > max_image_count {
+        if max_image_count > 0 && min_image_count > max_image_count {
             min_image_count = max_image_count;
         }
 
@@ -1,16 +1,51 @@
 use ash::vk;
 use track::Context;
 
-use self::buffer::Buffer;
-
 mod buffer;
 mod image;
+mod staging;
+
+pub use self::image::{StorageImage, TextureImage};
+pub use self::staging::{StagedImage, StagingBatch};
+
+/// Handle to a mesh uploaded into GPU buffers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MeshHandle {
+    index: usize,
+    pub index_count: u32,
+}
+
+/// Handle to a texture uploaded into a sampled GPU image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle {
+    index: usize,
+}
+
+/// Handle to a device-local buffer shaders read and write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StorageBufferHandle {
+    index: usize,
+    pub size: u64,
+}
+
+/// Handle to a device-local image shaders read and write, always in `GENERAL` layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StorageImageHandle {
+    index: usize,
+}
 
 #[derive(Default)]
 pub struct Resources {
     allocator: vma::Allocator,
     allocated_buffers: buffer::AllocatedBuffers,
     allocated_images: Vec<image::Image>,
+    /// Slots of destroyed textures are `None` and reused by the next uploads.
+    textures: Vec<Option<image::TextureImage>>,
+    readback_buffers: Vec<buffer::ReadbackBuffer>,
+    host_buffers: Vec<buffer::HostBuffer>,
+    /// Slots of destroyed storage buffers and images are `None` like the textures'.
+    storage_buffers: Vec<Option<buffer::StorageBuffer>>,
+    storage_images: Vec<Option<image::StorageImage>>,
 }
 
 impl Resources {
@@ -27,23 +62,189 @@ impl Resources {
             allocator,
             allocated_buffers: Default::default(),
             allocated_images: Default::default(),
+            textures: Default::default(),
+            readback_buffers: Default::default(),
+            host_buffers: Default::default(),
+            storage_buffers: Default::default(),
+            storage_images: Default::default(),
         })
     }
 
+    /// Creates device-local buffers for the mesh, their data is transferred once
+    /// `staging_batch` is recorded and submitted.
     #[inline(always)]
     pub fn uplaod_mesh(
         &mut self,
+        staging_batch: &mut StagingBatch,
         mesh: &crate::engine::asset_system::mesh::Mesh,
-    ) -> track::Result<()> {
-        self.allocated_buffers
-            .upload_mesh(self.allocator, mesh)
+    ) -> track::Result<MeshHandle> {
+        let index = self
+            .allocated_buffers
+            .upload_mesh(self.allocator, staging_batch, mesh)
             .track()?;
 
-        Ok(())
+        Ok(MeshHandle {
+            index,
+            index_count: mesh.indices.len() as u32,
+        })
+    }
+
+    /// Creates a sampled image for the texture, its pixels are transferred once
+    /// `staging_batch` is recorded and submitted.
+    ///
+    /// `usage` and `flags` are whatever the mip chain generation needs on top of sampling.
+    #[inline(always)]
+    pub fn upload_texture(
+        &mut self,
+        device: &ash::Device,
+        staging_batch: &mut StagingBatch,
+        texture: &crate::engine::asset_system::texture::Texture,
+        (usage, flags): (vk::ImageUsageFlags, vk::ImageCreateFlags),
+    ) -> track::Result<TextureHandle> {
+        let texture_image =
+            image::TextureImage::new(device, self.allocator, staging_batch, texture, usage, flags)
+                .track()?;
+
+        let index = insert_into_free_slot(&mut self.textures, texture_image);
+
+        Ok(TextureHandle { index })
+    }
+
+    /// Creates a storage buffer, its content is undefined until a shader or a transfer writes it.
+    #[inline(always)]
+    pub fn create_storage_buffer(&mut self, size: u64) -> track::Result<StorageBufferHandle> {
+        let storage_buffer = buffer::StorageBuffer::new(self.allocator, size).track()?;
+        let index = insert_into_free_slot(&mut self.storage_buffers, storage_buffer);
+
+        Ok(StorageBufferHandle { index, size })
+    }
+
+    /// Creates a storage image in `UNDEFINED` layout, the caller transitions it to `GENERAL`.
+    #[inline(always)]
+    pub fn create_storage_image(
+        &mut self,
+        device: &ash::Device,
+        image_extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> track::Result<StorageImageHandle> {
+        let storage_image =
+            image::StorageImage::new(device, self.allocator, image_extent, format).track()?;
+        let index = insert_into_free_slot(&mut self.storage_images, storage_image);
+
+        Ok(StorageImageHandle { index })
+    }
+
+    #[inline(always)]
+    pub fn storage_buffer(&self, storage_buffer_handle: StorageBufferHandle) -> vk::Buffer {
+        self.storage_buffers[storage_buffer_handle.index]
+            .as_ref()
+            .unwrap_or_else(|| panic!("Destroyed storage buffer: {storage_buffer_handle:?}"))
+            .buffer
+    }
+
+    #[inline(always)]
+    pub fn storage_image(
+        &self,
+        storage_image_handle: StorageImageHandle,
+    ) -> (vk::Image, vk::ImageView) {
+        let storage_image = self.storage_images[storage_image_handle.index]
+            .as_ref()
+            .unwrap_or_else(|| panic!("Destroyed storage image: {storage_image_handle:?}"));
+
+        (storage_image.image.image, storage_image.image_view)
+    }
+
+    #[inline(always)]
+    pub fn texture_view(&self, texture_handle: TextureHandle) -> vk::ImageView {
+        self.textures[texture_handle.index]
+            .as_ref()
+            .unwrap_or_else(|| panic!("Destroyed texture: {texture_handle:?}"))
+            .image_view
+    }
+
+    /// Destroys the views of every texture and storage image, must be called before dropping the resources.
+    #[inline]
+    pub fn destroy_image_views(&self, device: &ash::Device) {
+        self.textures
+            .iter()
+            .flatten()
+            .map(|texture_image| texture_image.image_view)
+            .chain(
+                self.storage_images
+                    .iter()
+                    .flatten()
+                    .map(|storage_image| storage_image.image_view),
+            )
+            .for_each(|image_view| unsafe {
+                device.destroy_image_view(image_view, None);
+            });
+    }
+
+    /// Frees the buffers of the mesh, the GPU must not use them anymore.
+    #[inline]
+    pub unsafe fn destroy_mesh(&mut self, mesh_handle: MeshHandle) {
+        self.allocated_buffers.mesh_buffers[mesh_handle.index]
+            .take()
+            .unwrap_or_else(|| panic!("Destroyed mesh: {mesh_handle:?}"))
+            .destroy(self.allocator);
+    }
+
+    /// Frees the image and the view of the texture, the GPU must not use them anymore.
+    #[inline]
+    pub unsafe fn destroy_texture(&mut self, device: &ash::Device, texture_handle: TextureHandle) {
+        let texture_image = self.textures[texture_handle.index]
+            .take()
+            .unwrap_or_else(|| panic!("Destroyed texture: {texture_handle:?}"));
+
+        device.destroy_image_view(texture_image.image_view, None);
+        vma::destroy_image(
+            self.allocator,
+            texture_image.image.image,
+            texture_image.image.allocation,
+        );
+    }
+
+    /// Frees the storage buffer, the GPU must not use it anymore.
+    #[inline]
+    pub unsafe fn destroy_storage_buffer(&mut self, storage_buffer_handle: StorageBufferHandle) {
+        let storage_buffer = self.storage_buffers[storage_buffer_handle.index]
+            .take()
+            .unwrap_or_else(|| panic!("Destroyed storage buffer: {storage_buffer_handle:?}"));
+
+        vma::destroy_buffer(
+            self.allocator,
+            storage_buffer.buffer,
+            storage_buffer.allocation,
+        );
+    }
+
+    /// Frees the image and the view of the storage image, the GPU must not use them anymore.
+    #[inline]
+    pub unsafe fn destroy_storage_image(
+        &mut self,
+        device: &ash::Device,
+        storage_image_handle: StorageImageHandle,
+    ) {
+        let storage_image = self.storage_images[storage_image_handle.index]
+            .take()
+            .unwrap_or_else(|| panic!("Destroyed storage image: {storage_image_handle:?}"));
+
+        device.destroy_image_view(storage_image.image_view, None);
+        vma::destroy_image(
+            self.allocator,
+            storage_image.image.image,
+            storage_image.image.allocation,
+        );
     }
 
+    /// Frees the staging buffers of a batch whose transfers have completed.
     #[inline(always)]
-    pub fn allocate_depth_image(
+    pub fn finish_upload(&self, staging_batch: StagingBatch) {
+        staging_batch.destroy(self.allocator);
+    }
+
+    #[inline(always)]
+    pub fn allocate_image(
         &mut self,
         image_info: &vk::ImageCreateInfo,
         allocation_info: &vma::AllocationCreateInfo,
@@ -57,25 +258,87 @@ impl Resources {
         Ok(image)
     }
 
+    #[inline]
+    pub fn destroy_image(&mut self, image: vk::Image) {
+        let position = self
+            .allocated_images
+            .iter()
+            .position(|allocated_image| allocated_image.image == image)
+            .unwrap_or_else(|| panic!("Unknown image: {image:?}"));
+
+        let allocated_image = self.allocated_images.swap_remove(position);
+        unsafe {
+            vma::destroy_image(
+                self.allocator,
+                allocated_image.image,
+                allocated_image.allocation,
+            )
+        };
+    }
+
     #[inline(always)]
-    pub unsafe fn bind_buffers_per_draw(
-        &self,
-        device: &ash::Device,
-        command_buffer: vk::CommandBuffer,
-    ) {
-        self.allocated_buffers
-            .vertex_buffers
+    pub fn allocate_readback_buffer(&mut self, size: u64) -> track::Result<vk::Buffer> {
+        let readback_buffer = buffer::ReadbackBuffer::new(self.allocator, size).track()?;
+        let buffer = readback_buffer.buffer;
+
+        self.readback_buffers.push(readback_buffer);
+
+        Ok(buffer)
+    }
+
+    /// Copies the whole content of a readback buffer into host memory.
+    ///
+    /// The caller must make sure the GPU finished writing into the buffer.
+    #[inline]
+    pub unsafe fn read_buffer(&self, buffer: vk::Buffer) -> track::Result<Vec<u8>> {
+        let readback_buffer = self
+            .readback_buffers
             .iter()
-            .zip(self.allocated_buffers.index_buffers.iter())
-            .for_each(|(vertex_buffers, index_buffer)| {
-                vertex_buffers.bind_buffer(device, command_buffer);
-                index_buffer.bind_buffer(device, command_buffer);
-            });
+            .find(|readback_buffer| readback_buffer.buffer == buffer)
+            .unwrap_or_else(|| panic!("Unknown readback buffer: {buffer:?}"));
+
+        readback_buffer.read(self.allocator).track()
     }
 
     #[inline(always)]
-    pub fn allocated_buffers_len(&self) -> usize {
-        self.allocated_buffers.vertex_buffers.len()
+    pub fn allocate_host_buffer(
+        &mut self,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+    ) -> track::Result<vk::Buffer> {
+        let host_buffer = buffer::HostBuffer::new(self.allocator, size, usage).track()?;
+        let buffer = host_buffer.buffer;
+
+        self.host_buffers.push(host_buffer);
+
+        Ok(buffer)
+    }
+
+    /// Copies `data` into the beginning of a host buffer.
+    ///
+    /// The caller must make sure the GPU doesn't read the buffer at the moment.
+    #[inline]
+    pub unsafe fn write_buffer(&self, buffer: vk::Buffer, data: &[u8]) -> track::Result<()> {
+        let host_buffer = self
+            .host_buffers
+            .iter()
+            .find(|host_buffer| host_buffer.buffer == buffer)
+            .unwrap_or_else(|| panic!("Unknown host buffer: {buffer:?}"));
+
+        host_buffer.write(self.allocator, data).track()
+    }
+
+    #[inline(always)]
+    pub unsafe fn bind_mesh(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        mesh_handle: MeshHandle,
+    ) {
+        self.allocated_buffers.mesh_buffers[mesh_handle.index]
+            .as_ref()
+            .unwrap_or_else(|| panic!("Destroyed mesh: {mesh_handle:?}"))
+            .bind(device, command_buffer);
     }
 }
 
@@ -83,29 +346,53 @@ impl Drop for Resources {
     fn drop(&mut self) {
         unsafe {
             self.allocated_buffers
-                .vertex_buffers
+                .mesh_buffers
                 .iter()
-                .for_each(|vertex_buffers| {
-                    vertex_buffers
-                        .buffers
-                        .iter()
-                        .zip(vertex_buffers.allocations.iter())
-                        .for_each(|(&buffer, &allocation)| {
-                            vma::destroy_buffer(self.allocator, buffer, allocation);
-                        })
-                });
+                .flatten()
+                .for_each(|mesh_buffers| mesh_buffers.destroy(self.allocator));
 
-            self.allocated_buffers
-                .index_buffers
+            self.host_buffers.iter().for_each(|host_buffer| {
+                vma::destroy_buffer(self.allocator, host_buffer.buffer, host_buffer.allocation)
+            });
+
+            self.readback_buffers.iter().for_each(|readback_buffer| {
+                vma::destroy_buffer(
+                    self.allocator,
+                    readback_buffer.buffer,
+                    readback_buffer.allocation,
+                )
+            });
+
+            self.storage_buffers
                 .iter()
-                .for_each(|index_buffer| {
+                .flatten()
+                .for_each(|storage_buffer| {
                     vma::destroy_buffer(
                         self.allocator,
-                        index_buffer.buffer,
-                        index_buffer.allocation,
+                        storage_buffer.buffer,
+                        storage_buffer.allocation,
+                    )
+                });
+
+            self.storage_images
+                .iter()
+                .flatten()
+                .for_each(|storage_image| {
+                    vma::destroy_image(
+                        self.allocator,
+                        storage_image.image.image,
+                        storage_image.image.allocation,
                     )
                 });
 
+            self.textures.iter().flatten().for_each(|texture_image| {
+                vma::destroy_image(
+                    self.allocator,
+                    texture_image.image.image,
+                    texture_image.image.allocation,
+                )
+            });
+
             self.allocated_images.iter().for_each(|allocated_image| {
                 vma::destroy_image(
                     self.allocator,
@@ -118,3 +405,20 @@ impl Drop for Resources {
         }
     }
 }
+
+/// Puts the value into the first slot freed by a destruction, or a new one, and returns its index.
+#[inline]
+fn insert_into_free_slot<T>(slots: &mut Vec<Option<T>>, value: T) -> usize {
+    match slots.iter().position(Option::is_none) {
+        Some(index) => {
+            slots[index] = Some(value);
+
+            index
+        }
+        None => {
+            slots.push(Some(value));
+
+            slots.len() - 1
+        }
+    }
+}
@@ -4,6 +4,8 @@ use ash::vk;
 use smallvec::SmallVec;
 use track::Context;
 
+use super::staging::StagingBatch;
+
 pub trait Buffer {
     unsafe fn bind_buffer(&self, device: &ash::Device, command_buffer: vk::CommandBuffer);
 }
@@ -66,31 +68,65 @@ impl IndexBuffer {
     }
 }
 
+/// Buffers of a single uploaded mesh.
+pub struct MeshBuffers {
+    pub vertex_buffers: VertexBuffers,
+    pub index_buffer: IndexBuffer,
+}
+
+impl MeshBuffers {
+    #[inline(always)]
+    pub unsafe fn bind(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        self.vertex_buffers.bind_buffer(device, command_buffer);
+        self.index_buffer.bind_buffer(device, command_buffer);
+    }
+
+    #[inline]
+    pub unsafe fn destroy(&self, allocator: vma::Allocator) {
+        self.vertex_buffers
+            .buffers
+            .iter()
+            .zip(self.vertex_buffers.allocations.iter())
+            .for_each(|(&buffer, &allocation)| {
+                vma::destroy_buffer(allocator, buffer, allocation);
+            });
+
+        vma::destroy_buffer(
+            allocator,
+            self.index_buffer.buffer,
+            self.index_buffer.allocation,
+        );
+    }
+}
+
 #[derive(Default)]
 pub struct AllocatedBuffers {
-    pub vertex_buffers: Vec<VertexBuffers>,
-    pub index_buffers: Vec<IndexBuffer>,
+    /// Slots of destroyed meshes are `None` and reused by the next uploads.
+    pub mesh_buffers: Vec<Option<MeshBuffers>>,
 }
 
 impl AllocatedBuffers {
     pub const VERTEX_BUFFERS_COUNT_PER_MESH: usize = 4;
 
+    /// Creates device-local buffers for the mesh, stages its data into `staging_batch`
+    /// and returns the index of its buffers in `mesh_buffers`.
     pub fn upload_mesh(
         &mut self,
         allocator: vma::Allocator,
+        staging_batch: &mut StagingBatch,
         mesh: &crate::engine::asset_system::mesh::Mesh,
-    ) -> track::Result<()> {
-        let size = mem::size_of_val(&*mesh.vertices);
+    ) -> track::Result<usize> {
         let allocation_info = vma::AllocationCreateInfo {
             usage: vma::MemoryUsage::AUTO,
-            flags: vma::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+            required_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
             ..Default::default()
         };
 
         let (buffer, allocation) = unsafe {
             Self::allocate_buffer(
                 allocator,
-                size,
+                staging_batch,
+                mem::size_of_val(&*mesh.vertices),
                 vk::BufferUsageFlags::VERTEX_BUFFER,
                 &allocation_info,
                 mesh.vertices.as_ptr().cast(),
@@ -102,19 +138,12 @@ impl AllocatedBuffers {
         let offsets = [0];
         let allocations = [allocation];
         let vertex_buffers = VertexBuffers::new(&buffers, &offsets, &allocations);
-        self.vertex_buffers.push(vertex_buffers);
-
-        let size = mem::size_of_val(&*mesh.indices);
-        let allocation_info = vma::AllocationCreateInfo {
-            usage: vma::MemoryUsage::AUTO,
-            flags: vma::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
-            ..Default::default()
-        };
 
         let (buffer, allocation) = unsafe {
             Self::allocate_buffer(
                 allocator,
-                size,
+                staging_batch,
+                mem::size_of_val(&*mesh.indices),
                 vk::BufferUsageFlags::INDEX_BUFFER,
                 &allocation_info,
                 mesh.indices.as_ptr().cast(),
@@ -128,13 +157,29 @@ impl AllocatedBuffers {
             vk::IndexType::UINT32,
             Default::default(),
         );
-        self.index_buffers.push(index_buffer);
 
-        Ok(())
+        let mesh_buffers = Some(MeshBuffers {
+            vertex_buffers,
+            index_buffer,
+        });
+
+        match self.mesh_buffers.iter().position(Option::is_none) {
+            Some(index) => {
+                self.mesh_buffers[index] = mesh_buffers;
+
+                Ok(index)
+            }
+            None => {
+                self.mesh_buffers.push(mesh_buffers);
+
+                Ok(self.mesh_buffers.len() - 1)
+            }
+        }
     }
 
     unsafe fn allocate_buffer(
         allocator: vma::Allocator,
+        staging_batch: &mut StagingBatch,
         size: usize,
         usage: vk::BufferUsageFlags,
         allocation_info: &vma::AllocationCreateInfo,
@@ -142,17 +187,142 @@ impl AllocatedBuffers {
     ) -> track::Result<(vk::Buffer, vma::Allocation)> {
         let buffer_info = vk::BufferCreateInfo::default()
             .size(size as u64)
-            .usage(usage);
+            .usage(usage | vk::BufferUsageFlags::TRANSFER_DST);
 
         let (buffer, allocation, _) =
             vma::create_buffer(allocator, &buffer_info, allocation_info).track()?;
 
-        let ptr_buffer = vma::map_memory(allocator, allocation).track()?;
+        staging_batch
+            .stage(allocator, ptr_data, size, buffer)
+            .track()?;
 
-        std::ptr::copy_nonoverlapping(ptr_data, ptr_buffer, size);
+        Ok((buffer, allocation))
+    }
+}
 
-        vma::unmap_memory(allocator, allocation);
+/// Host-visible buffer the GPU copies into, so its content can be read back on the CPU.
+pub struct ReadbackBuffer {
+    pub buffer: vk::Buffer,
+    pub allocation: vma::Allocation,
+    pub size: u64,
+}
 
-        Ok((buffer, allocation))
+impl ReadbackBuffer {
+    pub fn new(allocator: vma::Allocator, size: u64) -> track::Result<Self> {
+        let buffer_info = vk::BufferCreateInfo::default()
+            .size(size)
+            .usage(vk::BufferUsageFlags::TRANSFER_DST);
+        let allocation_info = vma::AllocationCreateInfo {
+            usage: vma::MemoryUsage::AUTO,
+            flags: vma::AllocationCreateFlags::HOST_ACCESS_RANDOM,
+            required_flags: vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+            ..Default::default()
+        };
+
+        let (buffer, allocation, _) =
+            unsafe { vma::create_buffer(allocator, &buffer_info, &allocation_info).track()? };
+
+        Ok(Self {
+            buffer,
+            allocation,
+            size,
+        })
+    }
+
+    pub unsafe fn read(&self, allocator: vma::Allocator) -> track::Result<Vec<u8>> {
+        let mut data = vec![0u8; self.size as usize];
+
+        let ptr_buffer = vma::map_memory(allocator, self.allocation).track()?;
+
+        std::ptr::copy_nonoverlapping(ptr_buffer.cast(), data.as_mut_ptr(), data.len());
+
+        vma::unmap_memory(allocator, self.allocation);
+
+        Ok(data)
+    }
+}
+
+/// Host-visible buffer the CPU writes into every frame, e.g. uniform or storage data.
+pub struct HostBuffer {
+    pub buffer: vk::Buffer,
+    pub allocation: vma::Allocation,
+    pub size: u64,
+}
+
+impl HostBuffer {
+    pub fn new(
+        allocator: vma::Allocator,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+    ) -> track::Result<Self> {
+        let buffer_info = vk::BufferCreateInfo::default().size(size).usage(usage);
+        let allocation_info = vma::AllocationCreateInfo {
+            usage: vma::MemoryUsage::AUTO,
+            flags: vma::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+            required_flags: vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+            ..Default::default()
+        };
+
+        let (buffer, allocation, _) =
+            unsafe { vma::create_buffer(allocator, &buffer_info, &allocation_info).track()? };
+
+        Ok(Self {
+            buffer,
+            allocation,
+            size,
+        })
+    }
+
+    pub unsafe fn write(&self, allocator: vma::Allocator, data: &[u8]) -> track::Result<()> {
+        assert!(
+            data.len() as u64 <= self.size,
+            "Writing {} bytes into a buffer of {} bytes",
+            data.len(),
+            self.size
+        );
+
+        let ptr_buffer = vma::map_memory(allocator, self.allocation).track()?;
+
+        std::ptr::copy_nonoverlapping(data.as_ptr(), ptr_buffer.cast(), data.len());
+
+        vma::unmap_memory(allocator, self.allocation);
+
+        Ok(())
+    }
+}
+
+/// Device-local buffer written and read by shaders, e.g. by compute dispatches, also usable
+/// as the source of indirect commands.
+pub struct StorageBuffer {
+    pub buffer: vk::Buffer,
+    pub allocation: vma::Allocation,
+    pub size: u64,
+}
+
+impl StorageBuffer {
+    pub fn new(allocator: vma::Allocator, size: u64) -> track::Result<Self> {
+        let buffer_info = vk::BufferCreateInfo::default().size(size).usage(
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::INDIRECT_BUFFER
+                | vk::BufferUsageFlags::VERTEX_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_SRC
+                | vk::BufferUsageFlags::TRANSFER_DST,
+        );
+        let allocation_info = vma::AllocationCreateInfo {
+            usage: vma::MemoryUsage::AUTO,
+            required_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            ..Default::default()
+        };
+
+        let (buffer, allocation, _) =
+            unsafe { vma::create_buffer(allocator, &buffer_info, &allocation_info).track()? };
+
+        Ok(Self {
+            buffer,
+            allocation,
+            size,
+        })
     }
 }
@@ -1,6 +1,10 @@
 use ash::vk;
 use track::Context;
 
+use crate::engine::asset_system::texture::Texture;
+
+use super::staging::{StagedImage, StagingBatch};
+
 pub struct Image {
     pub image: vk::Image,
     pub allocation: vma::Allocation,
@@ -18,3 +22,154 @@ impl Image {
         Ok(Self { image, allocation })
     }
 }
+
+/// Sampled image of a texture with a full mip chain, together with its view.
+pub struct TextureImage {
+    pub image: Image,
+    pub image_view: vk::ImageView,
+}
+
+impl TextureImage {
+    pub const FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+    /// Creates a device-local image for the texture and stages its pixels into the first mip level.
+    ///
+    /// `usage` and `flags` are added to what sampling and staging need, e.g. for the mip chain generation.
+    pub fn new(
+        device: &ash::Device,
+        allocator: vma::Allocator,
+        staging_batch: &mut StagingBatch,
+        texture: &Texture,
+        usage: vk::ImageUsageFlags,
+        flags: vk::ImageCreateFlags,
+    ) -> track::Result<Self> {
+        // NOTE: The staging copy reads the whole extent, whatever the size of the pixels.
+        texture.check_size().track()?;
+
+        let staged_image_extent = vk::Extent3D {
+            width: texture.width,
+            height: texture.height,
+            depth: 1,
+        };
+        let mip_levels = Self::mip_levels(texture.width, texture.height);
+
+        let image_info = vk::ImageCreateInfo::default()
+            .flags(flags)
+            .format(Self::FORMAT)
+            .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST | usage)
+            .extent(staged_image_extent)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .mip_levels(mip_levels)
+            .array_layers(1)
+            .image_type(vk::ImageType::TYPE_2D);
+
+        let allocation_info = vma::AllocationCreateInfo {
+            required_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            usage: vma::MemoryUsage::AUTO,
+            ..Default::default()
+        };
+
+        let image = Image::new(allocator, &image_info, &allocation_info).track()?;
+
+        let staged_image = StagedImage {
+            image: image.image,
+            image_extent: staged_image_extent,
+            mip_levels,
+        };
+
+        unsafe {
+            staging_batch
+                .stage_image(allocator, &texture.pixels, staged_image)
+                .track()?
+        };
+
+        // NOTE: Extra usages may be unsupported by the sRGB format, the view is only ever sampled.
+        let mut image_view_usage_info =
+            vk::ImageViewUsageCreateInfo::default().usage(vk::ImageUsageFlags::SAMPLED);
+        let image_view_info = vk::ImageViewCreateInfo::default()
+            .image(image.image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(Self::FORMAT)
+            .subresource_range(staged_image.subresource_range())
+            .push_next(&mut image_view_usage_info);
+
+        let image_view = unsafe { device.create_image_view(&image_view_info, None).track()? };
+
+        Ok(Self { image, image_view })
+    }
+
+    /// Count of levels in a full mip chain, down to 1x1.
+    #[inline(always)]
+    pub fn mip_levels(width: u32, height: u32) -> u32 {
+        width.max(height).max(1).ilog2() + 1
+    }
+}
+
+/// Device-local image written and read by shaders, kept in `GENERAL` layout for its whole life.
+pub struct StorageImage {
+    pub image: Image,
+    pub image_view: vk::ImageView,
+}
+
+impl StorageImage {
+    pub const SUBRESOURCE_RANGE: vk::ImageSubresourceRange = vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 1,
+    };
+
+    /// Creates the image in `UNDEFINED` layout, it must be transitioned to `GENERAL` before its first use.
+    pub fn new(
+        device: &ash::Device,
+        allocator: vma::Allocator,
+        image_extent: vk::Extent2D,
+        format: vk::Format,
+    ) -> track::Result<Self> {
+        let image_info = vk::ImageCreateInfo::default()
+            .format(format)
+            .usage(
+                vk::ImageUsageFlags::STORAGE
+                    | vk::ImageUsageFlags::SAMPLED
+                    | vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::TRANSFER_DST,
+            )
+            .extent(vk::Extent3D {
+                width: image_extent.width,
+                height: image_extent.height,
+                depth: 1,
+            })
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .mip_levels(1)
+            .array_layers(1)
+            .image_type(vk::ImageType::TYPE_2D);
+
+        let allocation_info = vma::AllocationCreateInfo {
+            required_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            usage: vma::MemoryUsage::AUTO,
+            ..Default::default()
+        };
+
+        let image = Image::new(allocator, &image_info, &allocation_info).track()?;
+
+        let image_view_info = vk::ImageViewCreateInfo::default()
+            .image(image.image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(format)
+            .subresource_range(Self::SUBRESOURCE_RANGE);
+
+        let image_view = match unsafe { device.create_image_view(&image_view_info, None) } {
+            Ok(image_view) => image_view,
+            Err(error) => {
+                unsafe { vma::destroy_image(allocator, image.image, image.allocation) };
+
+                return Err(error).track();
+            }
+        };
+
+        Ok(Self { image, image_view })
+    }
+}
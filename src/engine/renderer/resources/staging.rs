@@ -0,0 +1,212 @@
+use ash::vk;
+use track::Context;
+
+/// Image written by a [`StagingBatch`], only its first mip level receives data.
+#[derive(Clone, Copy)]
+pub struct StagedImage {
+    pub image: vk::Image,
+    pub image_extent: vk::Extent3D,
+    pub mip_levels: u32,
+}
+
+impl StagedImage {
+    #[inline(always)]
+    pub fn subresource_range(&self) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: self.mip_levels,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+}
+
+/// Host-visible copies of data waiting to be transferred into device-local buffers and images.
+///
+/// Many uploads are collected into one batch and recorded into a single command buffer.
+///
+/// Every mip level of the images is left in `TRANSFER_DST_OPTIMAL` layout, the rest of the mip chain
+/// is generated and the images are made readable by shaders afterwards.
+#[derive(Default)]
+pub struct StagingBatch {
+    staging_buffers: Vec<(vk::Buffer, vma::Allocation)>,
+    copies: Vec<(vk::Buffer, vk::Buffer, vk::BufferCopy)>,
+    image_copies: Vec<(vk::Buffer, StagedImage, vk::BufferImageCopy)>,
+}
+
+impl StagingBatch {
+    /// Copies `size` bytes into a new staging buffer and schedules their transfer into `dst_buffer`.
+    pub unsafe fn stage(
+        &mut self,
+        allocator: vma::Allocator,
+        ptr_data: *const std::ffi::c_void,
+        size: usize,
+        dst_buffer: vk::Buffer,
+    ) -> track::Result<()> {
+        let staging_buffer = self
+            .create_staging_buffer(allocator, ptr_data, size)
+            .track()?;
+
+        self.copies.push((
+            staging_buffer,
+            dst_buffer,
+            vk::BufferCopy {
+                src_offset: 0,
+                dst_offset: 0,
+                size: size as u64,
+            },
+        ));
+
+        Ok(())
+    }
+
+    /// Copies tightly packed `pixels` into a new staging buffer and schedules their transfer
+    /// into the first mip level of `dst_image`.
+    pub unsafe fn stage_image(
+        &mut self,
+        allocator: vma::Allocator,
+        pixels: &[u8],
+        dst_image: StagedImage,
+    ) -> track::Result<()> {
+        let staging_buffer = self
+            .create_staging_buffer(allocator, pixels.as_ptr().cast(), pixels.len())
+            .track()?;
+
+        self.image_copies.push((
+            staging_buffer,
+            dst_image,
+            vk::BufferImageCopy::default()
+                .image_subresource(vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .image_extent(dst_image.image_extent),
+        ));
+
+        Ok(())
+    }
+
+    unsafe fn create_staging_buffer(
+        &mut self,
+        allocator: vma::Allocator,
+        ptr_data: *const std::ffi::c_void,
+        size: usize,
+    ) -> track::Result<vk::Buffer> {
+        let buffer_info = vk::BufferCreateInfo::default()
+            .size(size as u64)
+            .usage(vk::BufferUsageFlags::TRANSFER_SRC);
+        let allocation_info = vma::AllocationCreateInfo {
+            usage: vma::MemoryUsage::AUTO,
+            flags: vma::AllocationCreateFlags::HOST_ACCESS_SEQUENTIAL_WRITE,
+            required_flags: vk::MemoryPropertyFlags::HOST_VISIBLE
+                | vk::MemoryPropertyFlags::HOST_COHERENT,
+            ..Default::default()
+        };
+
+        let (staging_buffer, allocation, _) =
+            vma::create_buffer(allocator, &buffer_info, &allocation_info).track()?;
+        self.staging_buffers.push((staging_buffer, allocation));
+
+        let ptr_buffer = vma::map_memory(allocator, allocation).track()?;
+        std::ptr::copy_nonoverlapping(ptr_data, ptr_buffer, size);
+        vma::unmap_memory(allocator, allocation);
+
+        Ok(staging_buffer)
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.copies.is_empty() && self.image_copies.is_empty()
+    }
+
+    /// Buffers the batch writes into, e.g. to transfer their ownership to another queue family.
+    #[inline(always)]
+    pub fn dst_buffers(&self) -> impl Iterator<Item = vk::Buffer> + '_ {
+        self.copies.iter().map(|(_, dst_buffer, _)| *dst_buffer)
+    }
+
+    /// Same as [`StagingBatch::dst_buffers`] for images.
+    #[inline(always)]
+    pub fn dst_images(&self) -> impl Iterator<Item = StagedImage> + '_ {
+        self.image_copies.iter().map(|(_, dst_image, _)| *dst_image)
+    }
+
+    /// Records the copies only.
+    pub unsafe fn record_copies(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        if !self.image_copies.is_empty() {
+            let image_memory_barriers: Vec<_> = self
+                .dst_images()
+                .map(|dst_image| {
+                    vk::ImageMemoryBarrier2::default()
+                        .dst_stage_mask(vk::PipelineStageFlags2::ALL_TRANSFER)
+                        .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                        .old_layout(vk::ImageLayout::UNDEFINED)
+                        .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                        .image(dst_image.image)
+                        .subresource_range(dst_image.subresource_range())
+                })
+                .collect();
+
+            device.cmd_pipeline_barrier2(
+                command_buffer,
+                &vk::DependencyInfo::default().image_memory_barriers(&image_memory_barriers),
+            );
+        }
+
+        self.copies
+            .iter()
+            .for_each(|(src_buffer, dst_buffer, region)| {
+                device.cmd_copy_buffer(
+                    command_buffer,
+                    *src_buffer,
+                    *dst_buffer,
+                    std::slice::from_ref(region),
+                );
+            });
+
+        self.image_copies
+            .iter()
+            .for_each(|(src_buffer, dst_image, region)| {
+                device.cmd_copy_buffer_to_image(
+                    command_buffer,
+                    *src_buffer,
+                    dst_image.image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    std::slice::from_ref(region),
+                );
+            });
+    }
+
+    /// Records every scheduled copy followed by a barrier making the buffers visible to vertex input
+    /// on the same queue.
+    pub unsafe fn record(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+        self.record_copies(device, command_buffer);
+
+        let memory_barriers = [vk::MemoryBarrier2::default()
+            .src_stage_mask(vk::PipelineStageFlags2::COPY)
+            .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+            .dst_stage_mask(vk::PipelineStageFlags2::VERTEX_INPUT)
+            .dst_access_mask(
+                vk::AccessFlags2::VERTEX_ATTRIBUTE_READ | vk::AccessFlags2::INDEX_READ,
+            )];
+
+        device.cmd_pipeline_barrier2(
+            command_buffer,
+            &vk::DependencyInfo::default().memory_barriers(&memory_barriers),
+        );
+    }
+
+    /// Frees the staging buffers, the recorded copies must have finished executing.
+    pub fn destroy(self, allocator: vma::Allocator) {
+        self.staging_buffers
+            .into_iter()
+            .for_each(|(buffer, allocation)| unsafe {
+                vma::destroy_buffer(allocator, buffer, allocation)
+            });
+    }
+}
@@ -0,0 +1,12 @@
+#version 450
+
+layout(location = 0) in vec3 color;
+layout(location = 1) in vec2 uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 1, binding = 0) uniform sampler2D base_color;
+
+void main() {
+    out_color = texture(base_color, uv);
+}
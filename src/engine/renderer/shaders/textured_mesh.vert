@@ -0,0 +1,26 @@
+#version 450
+
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec3 color;
+layout (location = 2) in vec2 uv;
+
+layout (location = 0) out vec3 out_color;
+layout (location = 1) out vec2 out_uv;
+
+layout (set = 0, binding = 0) uniform SceneUniforms {
+	mat4 view;
+	mat4 projection;
+	mat4 view_projection;
+	vec4 camera_position;
+} scene;
+
+layout (push_constant) uniform MeshPushConstants {
+	mat4 model;
+} push_constants;
+
+void main()
+{
+	gl_Position = scene.view_projection * push_constants.model * vec4(position, 1.0f);
+	out_color = color;
+	out_uv = uv;
+}
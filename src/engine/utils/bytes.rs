@@ -0,0 +1,30 @@
+/// Views a `#[repr(C)]` value as raw bytes, e.g. to fill push constants or uniform buffers.
+#[inline(always)]
+pub fn as_bytes<T: Sized>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts((value as *const T).cast(), std::mem::size_of::<T>()) }
+}
+
+/// Views a slice of `#[repr(C)]` values as raw bytes, e.g. to write vertices into a file.
+#[inline(always)]
+pub fn slice_as_bytes<T: Sized>(values: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(values.as_ptr().cast(), std::mem::size_of_val(values)) }
+}
+
+/// Copies raw bytes into values, the bytes don't have to be aligned for `T`.
+///
+/// # Safety
+/// Every bit pattern of the bytes must be a valid `T`.
+#[inline]
+pub unsafe fn copy_from_bytes<T: Copy>(bytes: &[u8]) -> Vec<T> {
+    let count = bytes.len() / std::mem::size_of::<T>();
+    let mut values = Vec::<T>::with_capacity(count);
+
+    std::ptr::copy_nonoverlapping(
+        bytes.as_ptr(),
+        values.as_mut_ptr().cast::<u8>(),
+        count * std::mem::size_of::<T>(),
+    );
+    values.set_len(count);
+
+    values
+}
@@ -1,2 +1,5 @@
+pub mod bytes;
 pub mod cstring;
+pub mod paths;
 pub mod profiling;
+pub mod shader_compiler;
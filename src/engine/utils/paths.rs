@@ -0,0 +1,135 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// Environment variable that overrides the directory every engine path is resolved against.
+pub const ROOT_DIR_ENV: &str = "VULKAN_LEARNING_ROOT";
+
+const ASSETS_DIR_NAME: &str = "assets";
+
+/// Directory the engine resolves its shaders and assets against.
+///
+/// Defaults to the closest directory containing the executable that has the assets, e.g. the crate
+/// directory for `target/debug`, or else the executable's directory, so the binary works regardless
+/// of the working directory and of where it was built.
+pub fn root_dir() -> PathBuf {
+    static EXECUTABLE_ROOT_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+    if let Some(root_dir) = std::env::var_os(ROOT_DIR_ENV) {
+        return PathBuf::from(root_dir);
+    }
+
+    EXECUTABLE_ROOT_DIR
+        .get_or_init(|| {
+            let executable_dir = std::env::current_exe()
+                .ok()
+                .and_then(|executable| executable.parent().map(Path::to_path_buf))
+                .unwrap_or_default();
+
+            executable_dir
+                .ancestors()
+                .find(|dir| dir.join(ASSETS_DIR_NAME).is_dir())
+                .unwrap_or(&executable_dir)
+                .to_path_buf()
+        })
+        .clone()
+}
+
+/// Directory of the compiled SPIR-V shaders, written by the build script,
+/// or of the cooked ones in shipping builds.
+#[inline]
+pub fn shaders_dir() -> PathBuf {
+    #[cfg(feature = "shipping")]
+    return cooked_dir().join(COOKED_SHADERS_DIR);
+
+    #[cfg(not(feature = "shipping"))]
+    PathBuf::from(env!("COMPILED_SHADERS_DIR"))
+}
+
+/// Directory of the GLSL shaders.
+#[inline]
+pub fn shader_sources_dir() -> PathBuf {
+    root_dir()
+        .join("src")
+        .join("engine")
+        .join("renderer")
+        .join("shaders")
+}
+
+#[inline]
+pub fn assets_dir() -> PathBuf {
+    root_dir().join(ASSETS_DIR_NAME)
+}
+
+/// Directory of the binary caches written from source assets, safe to delete at any time.
+#[inline]
+pub fn cache_dir() -> PathBuf {
+    root_dir().join("cache")
+}
+
+/// Directory the daily log files are written into.
+#[inline]
+pub fn logs_dir() -> PathBuf {
+    root_dir().join("src").join("engine").join("logs")
+}
+
+/// Name of the engine's directory in per-user directories.
+const USER_DIR_NAME: &str = "vulkan_learning";
+
+/// Per-user cache directory of the platform, e.g. `~/.cache/vulkan_learning` on Linux,
+/// or [`cache_dir`] if the platform has none.
+pub fn user_cache_dir() -> PathBuf {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    };
+
+    #[cfg(target_os = "windows")]
+    let platform_dir = env_dir("LOCALAPPDATA");
+    #[cfg(target_os = "macos")]
+    let platform_dir = env_dir("HOME").map(|home| home.join("Library").join("Caches"));
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let platform_dir =
+        env_dir("XDG_CACHE_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".cache")));
+
+    platform_dir.map_or_else(cache_dir, |dir| dir.join(USER_DIR_NAME))
+}
+
+/// File the driver's pipeline cache is kept in between runs, per user as it depends
+/// on the machine's GPU and driver rather than on the assets.
+#[inline]
+pub fn pipeline_cache_path() -> PathBuf {
+    user_cache_dir().join("pipelines.vkcache")
+}
+
+/// Subdirectory of [`cooked_dir`] the shaders are cooked into.
+pub const COOKED_SHADERS_DIR: &str = "shaders";
+
+/// Directory of the assets cooked into the runtime formats, mirroring [`assets_dir`].
+#[inline]
+pub fn cooked_dir() -> PathBuf {
+    root_dir().join("cooked")
+}
+
+/// Where the cooked version of `source` goes: the same place under `cooked_dir` as under `source_dir`,
+/// with `extension` appended to the file name, e.g. `models/cube.obj.vlmodel`.
+pub fn cooked_path(
+    source_dir: &Path,
+    cooked_dir: &Path,
+    source: &Path,
+    extension: &str,
+) -> PathBuf {
+    let relative_path = source
+        .strip_prefix(source_dir)
+        .ok()
+        .or_else(|| source.file_name().map(Path::new))
+        .unwrap_or(source);
+
+    let mut cooked_path = cooked_dir.join(relative_path).into_os_string();
+    cooked_path.push(".");
+    cooked_path.push(extension);
+
+    PathBuf::from(cooked_path)
+}
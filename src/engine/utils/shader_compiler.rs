@@ -0,0 +1,151 @@
+//! Compiles GLSL shaders into SPIR-V with `glslc`, or the compiler in the `GLSLC` variable.
+//!
+//! Shared by the build script, the cooker and the shader hot reload, so it only depends on `std`:
+//! the build script includes it with `#[path]`.
+
+use std::{
+    ffi::OsString,
+    fmt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Variable that overrides the compiler.
+pub const COMPILER_ENV: &str = "GLSLC";
+
+pub const SHADER_EXTENSIONS: [&str; 3] = ["vert", "frag", "comp"];
+
+/// Whether the file is a shader stage rather than e.g. an include.
+#[inline]
+pub fn is_shader_stage(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| SHADER_EXTENSIONS.contains(&extension))
+}
+
+pub struct ShaderCompiler {
+    compiler: OsString,
+    include_dir: PathBuf,
+}
+
+impl ShaderCompiler {
+    /// Compiler resolving `#include`s against `include_dir`.
+    #[inline]
+    pub fn new(include_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            compiler: std::env::var_os(COMPILER_ENV).unwrap_or_else(|| "glslc".into()),
+            include_dir: include_dir.into(),
+        }
+    }
+
+    /// Compiles the optimized shader into `output`, also writing the Makefile dependency file
+    /// listing its includes into `dependency_file` if there is one.
+    pub fn compile(
+        &self,
+        source: &Path,
+        output: &Path,
+        dependency_file: Option<&Path>,
+    ) -> Result<(), ShaderCompilationFailed> {
+        let mut command = Command::new(&self.compiler);
+        command.arg("-O").arg("-I").arg(&self.include_dir);
+        if let Some(dependency_file) = dependency_file {
+            command.arg("-MD").arg("-MF").arg(dependency_file);
+        }
+
+        let result = command
+            .arg(source)
+            .arg("-o")
+            .arg(output)
+            .output()
+            .map_err(|error| {
+                ShaderCompilationFailed(format!(
+                    "Failed to run `{}`, install the Vulkan SDK or point `{COMPILER_ENV}` to glslc: {error}",
+                    self.compiler.to_string_lossy()
+                ))
+            })?;
+
+        match result.status.success() {
+            true => Ok(()),
+            false => Err(ShaderCompilationFailed(
+                String::from_utf8_lossy(&result.stderr).into_owned(),
+            )),
+        }
+    }
+}
+
+/// Files listed in a Makefile dependency file written by `-MD`, e.g. `out.spv: mesh.vert common.glsl`.
+pub fn parse_dependencies(dependencies: &str) -> Vec<String> {
+    let dependencies = dependencies.replace("\\\n", " ").replace("\\\r\n", " ");
+    let Some((_, dependencies)) = dependencies.split_once(": ") else {
+        return Vec::new();
+    };
+
+    let mut paths = Vec::new();
+    let mut path = String::new();
+    let mut characters = dependencies.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match character {
+            // NOTE: Only escaped spaces are unescaped, Windows paths are full of backslashes.
+            '\\' if characters.peek() == Some(&' ') => path.extend(characters.next()),
+            ' ' | '\t' | '\n' | '\r' => {
+                if !path.is_empty() {
+                    paths.push(std::mem::take(&mut path));
+                }
+            }
+            _ => path.push(character),
+        }
+    }
+    if !path.is_empty() {
+        paths.push(path);
+    }
+
+    paths
+}
+
+/// Compiler diagnostics, or why the compiler couldn't run.
+#[derive(Debug)]
+pub struct ShaderCompilationFailed(pub String);
+
+impl fmt::Display for ShaderCompilationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Shader compilation failed: {}", self.0.trim_end())
+    }
+}
+
+impl std::error::Error for ShaderCompilationFailed {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dependencies() {
+        assert_eq!(
+            parse_dependencies("out/mesh.vert.spv: src/mesh.vert src/common.glsl\n"),
+            ["src/mesh.vert", "src/common.glsl"]
+        );
+    }
+
+    #[test]
+    fn dependencies_across_lines() {
+        assert_eq!(
+            parse_dependencies("mesh.spv: mesh.vert \\\n  common.glsl \\\r\n  lighting.glsl"),
+            ["mesh.vert", "common.glsl", "lighting.glsl"]
+        );
+    }
+
+    #[test]
+    fn dependencies_with_spaces_and_backslashes() {
+        assert_eq!(
+            parse_dependencies(r"C:\out\mesh.spv: C:\my\ shaders\mesh.vert C:\shaders\common.glsl"),
+            [r"C:\my shaders\mesh.vert", r"C:\shaders\common.glsl"]
+        );
+    }
+
+    #[test]
+    fn no_dependencies() {
+        assert!(parse_dependencies("").is_empty());
+        assert!(parse_dependencies("mesh.spv:").is_empty());
+    }
+}
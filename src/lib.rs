@@ -0,0 +1,3 @@
+#![feature(const_cstr_methods)]
+
+pub mod engine;
@@ -2,13 +2,15 @@ use std::panic;
 use tracing::{error, Level};
 use tracing_subscriber::{fmt, layer::SubscriberExt};
 use tracing_unwrap::OptionExt;
+use vulkan_learning::engine::utils::paths;
 
 pub fn init_logging() -> tracing_appender::non_blocking::WorkerGuard {
-    let file_appender = tracing_appender::rolling::daily("src/engine/logs", "engine.log");
+    let file_appender = tracing_appender::rolling::daily(paths::logs_dir(), "engine.log");
     let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
 
     let offset_time = fmt::time::OffsetTime::new(
-        time::UtcOffset::current_local_offset().unwrap(),
+        // NOTE: On Unix the local offset can't be determined soundly once other threads are running.
+        time::UtcOffset::current_local_offset().unwrap_or(time::UtcOffset::UTC),
         time::macros::format_description!("[hour]:[minute]:[second]"),
     );
 
@@ -1,30 +1,104 @@
 #![feature(panic_info_message)]
-#![feature(const_cstr_methods)]
 
-mod engine;
 mod logging;
 
+use std::path::PathBuf;
+
 use mimalloc::MiMalloc;
-use winit::{
-    event::{self, Event, WindowEvent},
-    platform::windows::WindowBuilderExtWindows,
-};
+use vulkan_learning::engine;
+use winit::event::{self, Event, WindowEvent};
+#[cfg(target_os = "windows")]
+use winit::platform::windows::WindowBuilderExtWindows;
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
+
+/// Arguments of the headless mode: `--headless <output_dir> [--frames N] [--width W] [--height H]`.
+struct HeadlessArgs {
+    output_dir: PathBuf,
+    frames: u32,
+    width: u32,
+    height: u32,
+}
+
+impl HeadlessArgs {
+    const DEFAULT_FRAMES: u32 = 1;
+    const DEFAULT_WIDTH: u32 = 640;
+    const DEFAULT_HEIGHT: u32 = 480;
+
+    fn from_env() -> Option<Self> {
+        let mut args = std::env::args().skip(1);
+        let mut output_dir = None;
+        let mut frames = Self::DEFAULT_FRAMES;
+        let mut width = Self::DEFAULT_WIDTH;
+        let mut height = Self::DEFAULT_HEIGHT;
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .unwrap_or_else(|| panic!("Missing value for `{arg}`"))
+            };
+
+            match arg.as_str() {
+                "--headless" => output_dir = Some(PathBuf::from(value())),
+                "--frames" => frames = value().parse().expect("`--frames` must be a number"),
+                "--width" => width = value().parse().expect("`--width` must be a number"),
+                "--height" => height = value().parse().expect("`--height` must be a number"),
+                _ => panic!("Unknown argument: {arg}"),
+            }
+        }
+
+        output_dir.map(|output_dir| Self {
+            output_dir,
+            frames,
+            width,
+            height,
+        })
+    }
+}
+
 fn main() {
+    match HeadlessArgs::from_env() {
+        Some(headless_args) => run_headless(headless_args),
+        None => run_windowed(),
+    }
+}
+
+fn run_headless(headless_args: HeadlessArgs) {
+    let _log_guard = logging::init_logging();
+
+    std::fs::create_dir_all(&headless_args.output_dir).unwrap();
+
+    let mut engine =
+        engine::Engine::new_headless(headless_args.width, headless_args.height).unwrap();
+
+    (0..headless_args.frames).for_each(|frame| {
+        engine.draw().unwrap();
+        engine
+            .capture(
+                headless_args
+                    .output_dir
+                    .join(format!("frame_{frame:04}.png")),
+            )
+            .unwrap();
+    });
+}
+
+fn run_windowed() {
     let event_loop = winit::event_loop::EventLoop::new();
-    let window = winit::window::WindowBuilder::new()
-        .with_resizable(false)
+    let window_builder = winit::window::WindowBuilder::new()
+        .with_resizable(true)
         .with_min_inner_size(winit::dpi::LogicalSize::new(640, 480))
-        .with_theme(Some(winit::window::Theme::Dark))
-        .with_title("Vulkan Learning")
-        .build(&event_loop)
-        .unwrap();
+        .with_title("Vulkan Learning");
+
+    #[cfg(target_os = "windows")]
+    let window_builder = window_builder.with_theme(Some(winit::window::Theme::Dark));
+
+    let window = window_builder.build(&event_loop).unwrap();
 
     let _log_guard = logging::init_logging();
 
-    let engine = engine::Engine::new(&window).unwrap();
+    let mut engine = engine::Engine::new(&window).unwrap();
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent { window_id, event } if window_id == window.id() => match event {
@@ -38,6 +112,10 @@ fn main() {
                     },
                 ..
             } => control_flow.set_exit(),
+            WindowEvent::Resized(size) => engine.resize(size.width, size.height),
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                engine.resize(new_inner_size.width, new_inner_size.height)
+            }
             _ => (),
         },
         Event::MainEventsCleared => engine.draw().unwrap(),